@@ -40,6 +40,7 @@ export type {
   CreateStreamParams,
   DistributeParams,
   DistributeEqualParams,
+  RemainderPolicy,
   TransactionResult,
   AccountInfo,
   AccountBalance,
@@ -211,6 +211,39 @@ describe('StellarService', () => {
           )
         ).rejects.toThrow(ValidationError);
       });
+
+      it('should reject an invalid remainder address', async () => {
+        await expect(
+          service.distributeEqual(
+            {
+              recipients: ['GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF'],
+              totalAmount: 1000n,
+              token: 'CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM',
+              remainderPolicy: { type: 'SendTo', address: 'invalid' },
+            },
+            testKeypair
+          )
+        ).rejects.toThrow(ValidationError);
+      });
+
+      it('should pass the remainder policy as the fifth argument', async () => {
+        const invokeSpy = vi
+          .spyOn(service as any, 'invokeContract')
+          .mockResolvedValue({ hash: 'abc', success: true });
+
+        await service.distributeEqual(
+          {
+            recipients: ['GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF'],
+            totalAmount: 1000n,
+            token: 'CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM',
+          },
+          testKeypair
+        );
+
+        const args = invokeSpy.mock.calls[0][2] as any[];
+        expect(args).toHaveLength(5);
+        expect(args[4].vec()[0].sym().toString()).toBe('Refund');
+      });
     });
 
     describe('withdraw validation', () => {
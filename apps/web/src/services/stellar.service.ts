@@ -31,6 +31,7 @@ import type {
   CreateStreamParams,
   DistributeParams,
   DistributeEqualParams,
+  RemainderPolicy,
   TransactionResult,
   AccountInfo,
   AccountBalance,
@@ -463,6 +464,7 @@ export class StellarService {
       new Address(params.token).toScVal(),
       nativeToScVal(params.totalAmount, { type: 'i128' }),
       xdr.ScVal.scvVec(recipientScVals),
+      this.remainderPolicyToScVal(params.remainderPolicy ?? { type: 'Refund' }),
     ];
 
     return this.invokeContract<void>(
@@ -755,6 +757,23 @@ export class StellarService {
     if (!params.token || !this.isValidAddress(params.token)) {
       throw new ValidationError('Invalid token address', 'token');
     }
+    if (
+      params.remainderPolicy?.type === 'SendTo' &&
+      !this.isValidAddress(params.remainderPolicy.address)
+    ) {
+      throw new ValidationError('Invalid remainder address', 'remainderPolicy');
+    }
+  }
+
+  /**
+   * Encode a remainder policy as a contract enum: a vector of the variant name and its fields
+   */
+  private remainderPolicyToScVal(policy: RemainderPolicy): xdr.ScVal {
+    const variant = xdr.ScVal.scvSymbol(policy.type);
+    if (policy.type === 'SendTo') {
+      return xdr.ScVal.scvVec([variant, new Address(policy.address).toScVal()]);
+    }
+    return xdr.ScVal.scvVec([variant]);
   }
 
   private isValidAddress(address: string): boolean {
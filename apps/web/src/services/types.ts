@@ -50,6 +50,14 @@ export interface DistributeParams {
   token: string;
 }
 
+/**
+ * Where the units left over by an equal split go, matching the contract's RemainderPolicy
+ */
+export type RemainderPolicy =
+  | { type: 'Refund' }
+  | { type: 'FirstRecipients' }
+  | { type: 'SendTo'; address: string };
+
 /**
  * Parameters for equal distribution
  */
@@ -60,6 +68,8 @@ export interface DistributeEqualParams {
   totalAmount: bigint;
   /** Token contract address */
   token: string;
+  /** Remainder handling (defaults to refunding the sender) */
+  remainderPolicy?: RemainderPolicy;
 }
 
 /**
@@ -1,92 +1,3527 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, Address, Env, Symbol, Vec};
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error, token,
+    xdr::{FromXdr, ToXdr},
+    Address, Bytes, BytesN, Env, String, Symbol, Vec,
+};
+
+const LEDGER_THRESHOLD: u32 = 518400; // ~30 days at 5s/ledger
+const LEDGER_BUMP: u32 = 535680; // ~31 days
+const LEDGER_SECONDS: u64 = 5;
+
+const BPS_DENOMINATOR: u32 = 10_000; // 100% in basis points
+const MAX_FEE_RATE: u32 = 500; // 5% in basis points
+
+// XDR of an account address up to its ed25519 key: ScVal::Address, ScAddress::Account, PublicKey::Ed25519
+const ACCOUNT_XDR_PREFIX: [u8; 12] = [0, 0, 0, 18, 0, 0, 0, 0, 0, 0, 0, 0];
+const ACCOUNT_KEY_LEN: u32 = 32;
+
+/// Custom errors for the contract
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    InvalidAmount = 3,
+    NoRecipients = 4,
+    LengthMismatch = 5,
+    ArithmeticOverflow = 6,
+    AmountTooSmall = 7,
+    InvalidShares = 8,
+    InvalidTimeRange = 9,
+    InvalidDeadline = 10,
+    InsufficientBalance = 11,
+    DuplicateToken = 12,
+    NothingToClaim = 13,
+    Expired = 14,
+    NotExpired = 15,
+    AlreadyReclaimed = 16,
+    DistributionNotFound = 17,
+    AllocationsNotFound = 18,
+    JobNotFound = 19,
+    JobNotOpen = 20,
+    JobCompleted = 21,
+    ExceedsEscrow = 22,
+    ReconciliationFailed = 23,
+    CampaignNotFound = 24,
+    AlreadyClaimed = 25,
+    InvalidProof = 26,
+    CampaignUnderfunded = 27,
+    FeeTooHigh = 28,
+    MalformedRecipients = 29,
+    UnsupportedRecipient = 30,
+    GroupNotFound = 31,
+    Unauthorized = 32,
+    MemberNotFound = 33,
+    ScheduleNotFound = 34,
+    ScheduleInactive = 35,
+    NothingDue = 36,
+}
+
+/// Amount sent to a single recipient by a distribution
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Receipt {
+    pub recipient: Address,
+    pub amount: i128,
+}
+
+/// What to do with the units left over when an equal split does not divide evenly
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemainderPolicy {
+    /// Leave the remainder with the sender; it is never pulled
+    Refund,
+    /// Give one extra unit to each of the first `remainder` recipients
+    FirstRecipients,
+    /// Send the whole remainder to a designated address
+    SendTo(Address),
+}
+
+/// Summary of an equal distribution, so totals reconcile to the unit
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EqualDistributionEvent {
+    pub sender: Address,
+    pub token: Address,
+    pub total_amount: i128,
+    pub amount_per_recipient: i128,
+    pub remainder: i128,
+    pub remainder_policy: RemainderPolicy,
+    pub fee_amount: i128,
+}
+
+/// Merkle airdrop campaign funded up front and claimed by recipients
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Campaign {
+    pub id: u64,
+    pub creator: Address,
+    pub token: Address,
+    pub merkle_root: BytesN<32>,
+    pub total_amount: i128,
+    pub claimed_amount: i128,
+    pub expiry: u64,
+    pub reclaimed: bool,
+}
+
+/// Prefix byte of leaf hashes, so a leaf can never be passed off as an inner node
+const LEAF_PREFIX: u8 = 0x00;
+/// Prefix byte of inner node hashes
+const NODE_PREFIX: u8 = 0x01;
+
+/// Hash of a campaign leaf:
+/// sha256(0x00 || index as 4 big-endian bytes || recipient XDR || amount as 16 big-endian bytes)
+pub fn campaign_leaf(env: &Env, index: u32, recipient: &Address, amount: i128) -> BytesN<32> {
+    let mut data = Bytes::from_array(env, &[LEAF_PREFIX]);
+    data.extend_from_array(&index.to_be_bytes());
+    data.append(&recipient.clone().to_xdr(env));
+    data.extend_from_array(&amount.to_be_bytes());
+    env.crypto().sha256(&data).to_bytes()
+}
+
+/// Hash of two sibling nodes: sha256(0x01 || lower child || higher child), ordered so
+/// proofs do not need to carry positions
+pub fn campaign_node(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = Bytes::from_array(env, &[NODE_PREFIX]);
+    data.extend_from_array(&left.to_array());
+    data.extend_from_array(&right.to_array());
+    env.crypto().sha256(&data).to_bytes()
+}
+
+/// Pack recipients for `distribute_packed`: per recipient, the 32-byte ed25519 account key
+/// followed by the amount as an unsigned LEB128 varint
+/// Only account (G...) addresses can be packed; contract addresses are rejected
+pub fn encode_recipients(env: &Env, recipients: &Vec<Address>, amounts: &Vec<i128>) -> Bytes {
+    if recipients.len() != amounts.len() {
+        panic_with_error!(env, Error::LengthMismatch);
+    }
+
+    let prefix = Bytes::from_array(env, &ACCOUNT_XDR_PREFIX);
+    let mut packed = Bytes::new(env);
+    for (recipient, amount) in recipients.iter().zip(amounts.iter()) {
+        let xdr = recipient.to_xdr(env);
+        if xdr.len() != prefix.len() + ACCOUNT_KEY_LEN || xdr.slice(0..prefix.len()) != prefix {
+            panic_with_error!(env, Error::UnsupportedRecipient);
+        }
+        if amount <= 0 {
+            panic_with_error!(env, Error::InvalidAmount);
+        }
+        packed.append(&xdr.slice(prefix.len()..));
+
+        let mut value = amount as u128;
+        while value >= 0x80 {
+            packed.push_back((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        packed.push_back(value as u8);
+    }
+
+    packed
+}
+
+/// Unpack the output of `encode_recipients`
+/// Truncated records, overlong or non-canonical varints and amounts above `i128::MAX`
+/// fail with `MalformedRecipients`
+pub fn decode_recipients(env: &Env, packed: &Bytes) -> (Vec<Address>, Vec<i128>) {
+    let mut recipients = Vec::new(env);
+    let mut amounts = Vec::new(env);
+
+    let len = packed.len();
+    let mut offset: u32 = 0;
+    while offset < len {
+        // A record is at least the key and one varint byte
+        if len - offset <= ACCOUNT_KEY_LEN {
+            panic_with_error!(env, Error::MalformedRecipients);
+        }
+        let mut xdr = Bytes::from_array(env, &ACCOUNT_XDR_PREFIX);
+        xdr.append(&packed.slice(offset..offset + ACCOUNT_KEY_LEN));
+        let recipient = Address::from_xdr(env, &xdr)
+            .unwrap_or_else(|_| panic_with_error!(env, Error::MalformedRecipients));
+        offset += ACCOUNT_KEY_LEN;
+
+        let mut amount: u128 = 0;
+        let mut shift: u32 = 0;
+        loop {
+            if offset >= len || shift > 126 {
+                panic_with_error!(env, Error::MalformedRecipients);
+            }
+            let byte = packed.get_unchecked(offset);
+            offset += 1;
+
+            let chunk = (byte & 0x7f) as u128;
+            if (chunk << shift) >> shift != chunk {
+                panic_with_error!(env, Error::MalformedRecipients);
+            }
+            amount |= chunk << shift;
+
+            if byte & 0x80 == 0 {
+                // A trailing zero group means the same value had a shorter encoding
+                if byte == 0 && shift > 0 {
+                    panic_with_error!(env, Error::MalformedRecipients);
+                }
+                break;
+            }
+            shift += 7;
+        }
+        if amount > i128::MAX as u128 {
+            panic_with_error!(env, Error::MalformedRecipients);
+        }
+
+        recipients.push_back(recipient);
+        amounts.push_back(amount as i128);
+    }
+
+    (recipients, amounts)
+}
+
+/// Stream created for a single recipient by `distribute_as_streams`
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamReceipt {
+    pub recipient: Address,
+    pub amount: i128,
+    pub stream_id: u64,
+}
+
+/// How a distribution computed each recipient's amount
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistributionMode {
+    Equal,
+    Weighted,
+    Percentage,
+    Streams,
+    Allocation,
+    Batch,
+    Group,
+    Scheduled,
+}
+
+/// Record of a completed distribution; `total_amount` includes the protocol fee
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Distribution {
+    pub id: u64,
+    pub sender: Address,
+    pub token: Address,
+    pub mode: DistributionMode,
+    pub total_amount: i128,
+    pub fee_amount: i128,
+    pub recipient_count: u32,
+    pub timestamp: u64,
+}
+
+/// Funds escrowed for a pull-based distribution
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllocationPool {
+    pub distribution_id: u64,
+    pub sender: Address,
+    pub token: Address,
+    pub total_amount: i128,
+    pub claimed_amount: i128,
+    pub deadline: u64,
+    pub reclaimed: bool,
+}
+
+/// Outcome of a resilient distribution: who was paid and who was left pending
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResilientSummary {
+    pub distribution_id: u64,
+    pub paid: Vec<Receipt>,
+    pub pending: Vec<Receipt>,
+    pub paid_amount: i128,
+    pub pending_amount: i128,
+    pub fee_amount: i128,
+}
+
+/// Saved recipient list with weights, reusable for recurring payouts
+/// `members` and `weights` are parallel; a member's share is its weight over the sum
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecipientGroup {
+    pub id: u64,
+    pub owner: Address,
+    pub name: String,
+    pub members: Vec<Address>,
+    pub weights: Vec<u32>,
+    pub managers: Vec<Address>,
+}
+
+/// Recurring payout escrowed up front and released one period at a time
+/// Period `n` (1-based) becomes due at `start_time + n * interval`
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub id: u64,
+    pub sender: Address,
+    pub token: Address,
+    pub recipients: Vec<Address>,
+    pub amounts: Vec<i128>,
+    pub period_amount: i128,
+    pub interval: u64,
+    pub start_time: u64,
+    pub periods: u32,
+    pub executed_periods: u32,
+    pub canceled: bool,
+    /// Fee rate in basis points when the schedule was created, charged on every release
+    pub fee_rate: u32,
+}
+
+/// Preview of a distribution: what each recipient receives after fees and rounding
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Quote {
+    pub receipts: Vec<Receipt>,
+    pub fee_amount: i128,
+    pub remainder: i128,
+    /// Everything pulled from the sender, including the fee and any remainder sent on
+    pub total_amount: i128,
+}
+
+/// Lifecycle of a chunked batch job
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchStatus {
+    /// Recipients can still be appended
+    Open,
+    /// Execution has started; the recipient list is frozen
+    Executing,
+    /// Every recipient has been paid and the job reconciled
+    Completed,
+}
+
+/// Large distribution escrowed up front and paid out in chunks
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchJob {
+    pub id: u64,
+    pub sender: Address,
+    pub token: Address,
+    pub total_amount: i128,
+    pub allocated_amount: i128,
+    pub fee_amount: i128,
+    pub paid_amount: i128,
+    /// Amounts that could not be transferred and were moved to the recipients' pending balances
+    pub pending_amount: i128,
+    /// Tokens the contract still holds for the job, measured from its balance on each transfer
+    pub escrow_amount: i128,
+    pub recipient_count: u32,
+    pub cursor: u32,
+    pub distribution_id: u64,
+    pub status: BatchStatus,
+}
+
+/// The subset of the payment-stream contract the distributor calls into
+#[contractclient(name = "PaymentStreamClient")]
+pub trait PaymentStreamInterface {
+    #[allow(clippy::too_many_arguments)]
+    fn create_stream(
+        env: Env,
+        sender: Address,
+        recipient: Address,
+        token: Address,
+        total_amount: i128,
+        initial_amount: i128,
+        start_time: u64,
+        end_time: u64,
+    ) -> u64;
+}
 
 #[contract]
 pub struct DistributorContract;
 
 #[contractimpl]
 impl DistributorContract {
-    /// Initialize the contract with an admin
-    pub fn initialize(env: Env, admin: Address) {
+    /// Initialize the contract with an admin and the payment-stream contract
+    /// used by `distribute_as_streams`
+    /// The admin is the initial fee collector and the fee rate starts at zero
+    pub fn initialize(env: Env, admin: Address, payment_stream: Address) {
+        if env.storage().instance().has(&Symbol::new(&env, "admin")) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
         admin.require_auth();
         env.storage()
             .instance()
             .set(&Symbol::new(&env, "admin"), &admin);
+        env.storage()
+            .instance()
+            .set(&Symbol::new(&env, "payment_stream"), &payment_stream);
+        env.storage()
+            .instance()
+            .set(&Symbol::new(&env, "fee_collector"), &admin);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    /// Set the fee taken from each distributed amount, in basis points (admin only)
+    /// Stream distributions, Merkle campaigns and a remainder sent with
+    /// `RemainderPolicy::SendTo` are not charged
+    pub fn set_fee_rate(env: Env, fee_rate: u32) {
+        Self::require_admin(&env);
+
+        if fee_rate > MAX_FEE_RATE {
+            panic_with_error!(&env, Error::FeeTooHigh);
+        }
+
+        env.storage()
+            .instance()
+            .set(&Symbol::new(&env, "fee_rate"), &fee_rate);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    /// Set the address that receives distribution fees (admin only)
+    pub fn set_fee_collector(env: Env, fee_collector: Address) {
+        Self::require_admin(&env);
+
+        env.storage()
+            .instance()
+            .set(&Symbol::new(&env, "fee_collector"), &fee_collector);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    /// Get the distribution fee rate in basis points
+    pub fn get_fee_rate(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&Symbol::new(&env, "fee_rate"))
+            .unwrap_or(0)
+    }
+
+    /// Get the address that receives distribution fees
+    pub fn get_fee_collector(env: Env) -> Option<Address> {
+        env.storage()
+            .instance()
+            .get(&Symbol::new(&env, "fee_collector"))
     }
 
     /// Distribute equal amounts to multiple recipients
-    /// Total amount is divided equally among all recipients
+    /// Total amount is divided equally among all recipients, and any
+    /// remainder is handled according to `remainder_policy`
     pub fn distribute_equal(
-        _env: Env,
+        env: Env,
         sender: Address,
-        _token: Address,
+        token: Address,
         total_amount: i128,
         recipients: Vec<Address>,
-    ) {
+        remainder_policy: RemainderPolicy,
+    ) -> Vec<Receipt> {
         sender.require_auth();
 
+        let quote = Self::quote_equal(
+            env.clone(),
+            total_amount,
+            recipients.clone(),
+            remainder_policy.clone(),
+        );
+        let distribution_id = Self::record_distribution(
+            &env,
+            &sender,
+            &token,
+            DistributionMode::Equal,
+            quote.total_amount,
+            quote.fee_amount,
+            recipients.len(),
+        );
+
+        let receipts = Self::transfer_all(
+            &env,
+            distribution_id,
+            &sender,
+            &token,
+            &quote.receipts,
+            quote.fee_amount,
+        );
+
+        if let RemainderPolicy::SendTo(remainder_recipient) = &remainder_policy {
+            if quote.remainder > 0 {
+                token::Client::new(&env, &token).transfer(
+                    &sender,
+                    remainder_recipient,
+                    &quote.remainder,
+                );
+            }
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "equal_distribution"), sender.clone()),
+            EqualDistributionEvent {
+                sender,
+                token,
+                total_amount,
+                amount_per_recipient: total_amount / recipients.len() as i128,
+                remainder: quote.remainder,
+                remainder_policy,
+                fee_amount: quote.fee_amount,
+            },
+        );
+
+        receipts
+    }
+
+    /// Preview `distribute_equal` without moving funds
+    pub fn quote_equal(
+        env: Env,
+        total_amount: i128,
+        recipients: Vec<Address>,
+        remainder_policy: RemainderPolicy,
+    ) -> Quote {
         let recipient_count = recipients.len() as i128;
-        assert!(recipient_count > 0, "No recipients provided");
-        assert!(total_amount > 0, "Amount must be positive");
+        if recipient_count <= 0 {
+            panic_with_error!(&env, Error::NoRecipients);
+        }
+        if total_amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
 
         let amount_per_recipient = total_amount / recipient_count;
-        assert!(amount_per_recipient > 0, "Amount too small to distribute");
+        if amount_per_recipient <= 0 {
+            panic_with_error!(&env, Error::AmountTooSmall);
+        }
+        let remainder = total_amount % recipient_count;
 
-        // TODO: Transfer tokens from sender to each recipient
-        // This would use the token contract's transfer function
-        for _recipient in recipients.iter() {
-            // token.transfer(sender, recipient, amount_per_recipient)
+        let mut amounts = Vec::new(&env);
+        for i in 0..recipients.len() {
+            let bonus = match remainder_policy {
+                RemainderPolicy::FirstRecipients if (i as i128) < remainder => 1,
+                _ => 0,
+            };
+            amounts.push_back(amount_per_recipient + bonus);
         }
+
+        // A refunded remainder is never pulled from the sender
+        let pulled = amount_per_recipient * recipient_count
+            + match remainder_policy {
+                RemainderPolicy::Refund => 0,
+                _ => remainder,
+            };
+
+        Self::build_quote(&env, &recipients, &amounts, remainder, pulled)
     }
 
     /// Distribute weighted amounts to multiple recipients
     /// Each recipient receives their specified amount
     pub fn distribute_weighted(
-        _env: Env,
+        env: Env,
         sender: Address,
-        _token: Address,
+        token: Address,
         recipients: Vec<Address>,
         amounts: Vec<i128>,
-    ) {
+    ) -> Vec<Receipt> {
         sender.require_auth();
 
-        assert!(
-            recipients.len() == amounts.len(),
-            "Recipients and amounts must match"
+        let quote = Self::quote_weighted(env.clone(), recipients.clone(), amounts);
+        let distribution_id = Self::record_distribution(
+            &env,
+            &sender,
+            &token,
+            DistributionMode::Weighted,
+            quote.total_amount,
+            quote.fee_amount,
+            recipients.len(),
         );
-        assert!(!recipients.is_empty(), "No recipients provided");
 
-        // TODO: Transfer tokens from sender to each recipient with their specified amount
-        for i in 0..recipients.len() {
-            let _recipient = recipients.get(i).unwrap();
-            let _amount = amounts.get(i).unwrap();
-            // token.transfer(sender, recipient, amount)
+        Self::transfer_all(
+            &env,
+            distribution_id,
+            &sender,
+            &token,
+            &quote.receipts,
+            quote.fee_amount,
+        )
+    }
+
+    /// Preview `distribute_weighted` without moving funds
+    pub fn quote_weighted(env: Env, recipients: Vec<Address>, amounts: Vec<i128>) -> Quote {
+        let total_amount = Self::validate_amounts(&env, &recipients, &amounts);
+        Self::build_quote(&env, &recipients, &amounts, 0, total_amount)
+    }
+
+    /// Allow or stop `operator` triggering distributions funded from the treasury's allowance
+    pub fn set_operator(env: Env, treasury: Address, operator: Address, enabled: bool) {
+        treasury.require_auth();
+
+        let key = (
+            Symbol::new(&env, "operator"),
+            treasury.clone(),
+            operator.clone(),
+        );
+        if enabled {
+            env.storage().persistent().set(&key, &true);
+            env.storage()
+                .persistent()
+                .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+        } else {
+            env.storage().persistent().remove(&key);
         }
+
+        env.events().publish(
+            (Symbol::new(&env, "operator_set"), treasury),
+            (operator, enabled),
+        );
     }
 
-    /// Get the admin address
-    pub fn get_admin(env: Env) -> Option<Address> {
-        env.storage().instance().get(&Symbol::new(&env, "admin"))
+    /// Check whether `operator` may spend the treasury's allowance
+    pub fn is_operator(env: Env, treasury: Address, operator: Address) -> bool {
+        env.storage()
+            .persistent()
+            .has(&(Symbol::new(&env, "operator"), treasury, operator))
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use soroban_sdk::testutils::Address as _;
+    /// Weighted distribution funded with `transfer_from` against the allowance the
+    /// treasury approved for this contract, triggered by one of its operators
+    /// The approved allowance caps what operators can spend in total
+    pub fn distribute_from(
+        env: Env,
+        operator: Address,
+        treasury: Address,
+        token: Address,
+        recipients: Vec<Address>,
+        amounts: Vec<i128>,
+    ) -> Vec<Receipt> {
+        operator.require_auth();
 
-    #[test]
-    fn test_initialize() {
-        let env = Env::default();
-        env.mock_all_auths();
+        if !Self::is_operator(env.clone(), treasury.clone(), operator.clone()) {
+            panic_with_error!(&env, Error::Unauthorized);
+        }
 
-        let contract_id = env.register(DistributorContract, ());
-        let client = DistributorContractClient::new(&env, &contract_id);
+        let quote = Self::quote_weighted(env.clone(), recipients.clone(), amounts);
+        token::Client::new(&env, &token).transfer_from(
+            &env.current_contract_address(),
+            &treasury,
+            &env.current_contract_address(),
+            &quote.total_amount,
+        );
 
-        let admin = Address::generate(&env);
-        client.initialize(&admin);
+        let distribution_id = Self::record_distribution(
+            &env,
+            &treasury,
+            &token,
+            DistributionMode::Weighted,
+            quote.total_amount,
+            quote.fee_amount,
+            recipients.len(),
+        );
+        env.events().publish(
+            (Symbol::new(&env, "operator_distribution"), distribution_id),
+            (treasury, operator),
+        );
 
-        let stored_admin = client.get_admin();
-        assert_eq!(stored_admin, Some(admin));
+        Self::transfer_all(
+            &env,
+            distribution_id,
+            &env.current_contract_address(),
+            &token,
+            &quote.receipts,
+            quote.fee_amount,
+        )
+    }
+
+    /// Run a weighted distribution for each `(token, recipients, amounts)` batch under one authorization
+    /// Every batch is validated against the sender's balance before any transfer,
+    /// and each token gets its own distribution record and event
+    pub fn distribute_multi(
+        env: Env,
+        sender: Address,
+        batches: Vec<(Address, Vec<Address>, Vec<i128>)>,
+    ) -> Vec<u64> {
+        sender.require_auth();
+
+        if batches.is_empty() {
+            panic_with_error!(&env, Error::NoRecipients);
+        }
+
+        let mut tokens: Vec<Address> = Vec::new(&env);
+        let mut quotes: Vec<Quote> = Vec::new(&env);
+        for (token, recipients, amounts) in batches.iter() {
+            if tokens.contains(&token) {
+                panic_with_error!(&env, Error::DuplicateToken);
+            }
+
+            let quote = Self::quote_weighted(env.clone(), recipients, amounts);
+            if token::Client::new(&env, &token).balance(&sender) < quote.total_amount {
+                panic_with_error!(&env, Error::InsufficientBalance);
+            }
+
+            tokens.push_back(token);
+            quotes.push_back(quote);
+        }
+
+        let mut distribution_ids = Vec::new(&env);
+        for (token, quote) in tokens.iter().zip(quotes.iter()) {
+            let distribution_id = Self::record_distribution(
+                &env,
+                &sender,
+                &token,
+                DistributionMode::Weighted,
+                quote.total_amount,
+                quote.fee_amount,
+                quote.receipts.len(),
+            );
+            Self::transfer_all(
+                &env,
+                distribution_id,
+                &sender,
+                &token,
+                &quote.receipts,
+                quote.fee_amount,
+            );
+            distribution_ids.push_back(distribution_id);
+        }
+
+        distribution_ids
+    }
+
+    /// Weighted distribution taking recipients packed with `encode_recipients`,
+    /// which is far smaller in transaction bytes than two vectors
+    pub fn distribute_packed(
+        env: Env,
+        sender: Address,
+        token: Address,
+        packed: Bytes,
+    ) -> Vec<Receipt> {
+        let (recipients, amounts) = decode_recipients(&env, &packed);
+        Self::distribute_weighted(env, sender, token, recipients, amounts)
+    }
+
+    /// Create a named recipient group owned by `owner`
+    pub fn create_group(
+        env: Env,
+        owner: Address,
+        name: String,
+        members: Vec<Address>,
+        weights: Vec<u32>,
+    ) -> u64 {
+        owner.require_auth();
+
+        let count_key = Symbol::new(&env, "group_count");
+        let group_id: u64 = env.storage().instance().get(&count_key).unwrap_or(0) + 1;
+        env.storage().instance().set(&count_key, &group_id);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        let mut group = RecipientGroup {
+            id: group_id,
+            owner: owner.clone(),
+            name,
+            members: Vec::new(&env),
+            weights: Vec::new(&env),
+            managers: Vec::new(&env),
+        };
+        env.events().publish(
+            (Symbol::new(&env, "group_created"), group_id),
+            (owner.clone(), group.name.clone()),
+        );
+        Self::upsert_members(&env, &mut group, &owner, &members, &weights);
+        Self::save_group(&env, &group);
+
+        group_id
+    }
+
+    /// Add members or change their weights (owner or manager)
+    pub fn update_group(
+        env: Env,
+        caller: Address,
+        group_id: u64,
+        members: Vec<Address>,
+        weights: Vec<u32>,
+    ) {
+        let mut group = Self::get_group(env.clone(), group_id);
+        Self::require_group_editor(&env, &group, &caller);
+
+        Self::upsert_members(&env, &mut group, &caller, &members, &weights);
+        Self::save_group(&env, &group);
+    }
+
+    /// Remove a member from a group (owner or manager)
+    pub fn remove_member(env: Env, caller: Address, group_id: u64, member: Address) {
+        let mut group = Self::get_group(env.clone(), group_id);
+        Self::require_group_editor(&env, &group, &caller);
+
+        let index = group
+            .members
+            .first_index_of(&member)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::MemberNotFound));
+        group.members.remove(index);
+        group.weights.remove(index);
+        Self::save_group(&env, &group);
+
+        env.events().publish(
+            (Symbol::new(&env, "member_removed"), group_id),
+            (caller, member),
+        );
+    }
+
+    /// Let `manager` edit a group's members (owner only)
+    pub fn add_group_manager(env: Env, group_id: u64, manager: Address) {
+        let mut group = Self::get_group(env.clone(), group_id);
+        group.owner.require_auth();
+
+        if !group.managers.contains(&manager) {
+            group.managers.push_back(manager.clone());
+            Self::save_group(&env, &group);
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "group_manager_added"), group_id),
+            manager,
+        );
+    }
+
+    /// Revoke a manager's edit rights (owner only)
+    pub fn remove_group_manager(env: Env, group_id: u64, manager: Address) {
+        let mut group = Self::get_group(env.clone(), group_id);
+        group.owner.require_auth();
+
+        if let Some(index) = group.managers.first_index_of(&manager) {
+            group.managers.remove(index);
+            Self::save_group(&env, &group);
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "group_manager_removed"), group_id),
+            manager,
+        );
+    }
+
+    /// Split `total_amount` across a group by weight, funded by the group owner
+    /// Amounts are rounded on cumulative weights, so the whole total is paid out
+    pub fn distribute_to_group(
+        env: Env,
+        group_id: u64,
+        token: Address,
+        total_amount: i128,
+    ) -> Vec<Receipt> {
+        let group = Self::get_group(env.clone(), group_id);
+        group.owner.require_auth();
+
+        if group.members.is_empty() {
+            panic_with_error!(&env, Error::NoRecipients);
+        }
+        if total_amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        let mut total_weight: u32 = 0;
+        for weight in group.weights.iter() {
+            total_weight = total_weight
+                .checked_add(weight)
+                .unwrap_or_else(|| panic_with_error!(&env, Error::ArithmeticOverflow));
+        }
+
+        let mut amounts = Vec::new(&env);
+        let mut cumulative_weight: u32 = 0;
+        let mut paid: i128 = 0;
+        for weight in group.weights.iter() {
+            cumulative_weight += weight;
+            let cumulative_amount = Self::share_of(total_amount, cumulative_weight, total_weight);
+            if cumulative_amount == paid {
+                panic_with_error!(&env, Error::AmountTooSmall);
+            }
+            amounts.push_back(cumulative_amount - paid);
+            paid = cumulative_amount;
+        }
+
+        let quote = Self::build_quote(&env, &group.members, &amounts, 0, total_amount);
+        let distribution_id = Self::record_distribution(
+            &env,
+            &group.owner,
+            &token,
+            DistributionMode::Group,
+            quote.total_amount,
+            quote.fee_amount,
+            group.members.len(),
+        );
+
+        Self::transfer_all(
+            &env,
+            distribution_id,
+            &group.owner,
+            &token,
+            &quote.receipts,
+            quote.fee_amount,
+        )
+    }
+
+    /// Get a recipient group by id
+    pub fn get_group(env: Env, group_id: u64) -> RecipientGroup {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "group"), group_id))
+            .unwrap_or_else(|| panic_with_error!(&env, Error::GroupNotFound))
+    }
+
+    /// Escrow `periods` rounds of a weighted distribution, one due every `interval` seconds
+    pub fn create_schedule(
+        env: Env,
+        sender: Address,
+        token: Address,
+        recipients: Vec<Address>,
+        amounts: Vec<i128>,
+        interval: u64,
+        periods: u32,
+    ) -> u64 {
+        sender.require_auth();
+
+        if interval == 0 {
+            panic_with_error!(&env, Error::InvalidTimeRange);
+        }
+        if periods == 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+        let period_amount = Self::validate_amounts(&env, &recipients, &amounts);
+        let escrowed = period_amount
+            .checked_mul(periods as i128)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::ArithmeticOverflow));
+
+        token::Client::new(&env, &token).transfer(
+            &sender,
+            &env.current_contract_address(),
+            &escrowed,
+        );
+
+        let count_key = Symbol::new(&env, "schedule_count");
+        let schedule_id: u64 = env.storage().instance().get(&count_key).unwrap_or(0) + 1;
+        env.storage().instance().set(&count_key, &schedule_id);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        let schedule = Schedule {
+            id: schedule_id,
+            sender,
+            token,
+            recipients,
+            amounts,
+            period_amount,
+            interval,
+            start_time: env.ledger().timestamp(),
+            periods,
+            executed_periods: 0,
+            canceled: false,
+            fee_rate: Self::get_fee_rate(env.clone()),
+        };
+        Self::save_schedule(&env, &schedule);
+
+        env.events().publish(
+            (Symbol::new(&env, "schedule_created"), schedule_id),
+            schedule,
+        );
+
+        schedule_id
+    }
+
+    /// Release the next due period of a schedule; callable by anyone
+    /// The fee is charged at the rate in force when the schedule was created, and
+    /// amounts that cannot be transferred become pending balances.
+    /// Returns the id of the distribution recording the release
+    pub fn execute_due(env: Env, schedule_id: u64) -> u64 {
+        let mut schedule = Self::get_schedule(env.clone(), schedule_id);
+
+        if schedule.canceled || schedule.executed_periods == schedule.periods {
+            panic_with_error!(&env, Error::ScheduleInactive);
+        }
+        let elapsed = env.ledger().timestamp() - schedule.start_time;
+        if elapsed / schedule.interval <= schedule.executed_periods as u64 {
+            panic_with_error!(&env, Error::NothingDue);
+        }
+
+        schedule.executed_periods += 1;
+        Self::save_schedule(&env, &schedule);
+
+        let (net_amounts, fee_amount) =
+            Self::deduct_fees_at(&env, &schedule.amounts, schedule.fee_rate);
+        let distribution_id = Self::record_distribution(
+            &env,
+            &schedule.sender,
+            &schedule.token,
+            DistributionMode::Scheduled,
+            schedule.period_amount,
+            fee_amount,
+            schedule.recipients.len(),
+        );
+
+        let token_client = token::Client::new(&env, &schedule.token);
+        for (recipient, amount) in schedule.recipients.iter().zip(net_amounts.iter()) {
+            let receipt = Receipt { recipient, amount };
+            let transferred = token_client
+                .try_transfer(&env.current_contract_address(), &receipt.recipient, &amount)
+                .is_ok();
+
+            if transferred {
+                env.events().publish(
+                    (Symbol::new(&env, "recipient_paid"), distribution_id),
+                    receipt,
+                );
+            } else {
+                Self::credit_pending(&env, distribution_id, &schedule.token, &receipt);
+            }
+        }
+        Self::pay_fee(
+            &env,
+            distribution_id,
+            &env.current_contract_address(),
+            &schedule.token,
+            fee_amount,
+        );
+
+        env.events().publish(
+            (Symbol::new(&env, "schedule_executed"), schedule_id),
+            (schedule.executed_periods, distribution_id),
+        );
+
+        distribution_id
+    }
+
+    /// Stop a schedule and return the escrow for periods not yet released
+    pub fn cancel_schedule(env: Env, schedule_id: u64) -> i128 {
+        let mut schedule = Self::get_schedule(env.clone(), schedule_id);
+        schedule.sender.require_auth();
+
+        if schedule.canceled || schedule.executed_periods == schedule.periods {
+            panic_with_error!(&env, Error::ScheduleInactive);
+        }
+
+        let refund =
+            schedule.period_amount * (schedule.periods - schedule.executed_periods) as i128;
+        schedule.canceled = true;
+        Self::save_schedule(&env, &schedule);
+
+        token::Client::new(&env, &schedule.token).transfer(
+            &env.current_contract_address(),
+            &schedule.sender,
+            &refund,
+        );
+
+        env.events().publish(
+            (Symbol::new(&env, "schedule_canceled"), schedule_id),
+            refund,
+        );
+
+        refund
+    }
+
+    /// Get a schedule by id
+    pub fn get_schedule(env: Env, schedule_id: u64) -> Schedule {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "schedule"), schedule_id))
+            .unwrap_or_else(|| panic_with_error!(&env, Error::ScheduleNotFound))
+    }
+
+    /// Distribute weighted amounts without letting one failing recipient abort the batch
+    /// The total is escrowed first; amounts that cannot be transferred are kept
+    /// as pending balances the recipient can later pull with `claim_pending`
+    pub fn distribute_weighted_resilient(
+        env: Env,
+        sender: Address,
+        token: Address,
+        recipients: Vec<Address>,
+        amounts: Vec<i128>,
+    ) -> ResilientSummary {
+        sender.require_auth();
+
+        let quote = Self::quote_weighted(env.clone(), recipients.clone(), amounts);
+        let fee_amount = quote.fee_amount;
+        let distribution_id = Self::record_distribution(
+            &env,
+            &sender,
+            &token,
+            DistributionMode::Weighted,
+            quote.total_amount,
+            fee_amount,
+            recipients.len(),
+        );
+
+        let token_client = token::Client::new(&env, &token);
+        token_client.transfer(
+            &sender,
+            &env.current_contract_address(),
+            &quote.total_amount,
+        );
+        Self::pay_fee(
+            &env,
+            distribution_id,
+            &env.current_contract_address(),
+            &token,
+            fee_amount,
+        );
+
+        let mut summary = ResilientSummary {
+            distribution_id,
+            paid: Vec::new(&env),
+            pending: Vec::new(&env),
+            paid_amount: 0,
+            pending_amount: 0,
+            fee_amount,
+        };
+
+        for receipt in quote.receipts.iter() {
+            let amount = receipt.amount;
+            let transferred = token_client
+                .try_transfer(&env.current_contract_address(), &receipt.recipient, &amount)
+                .is_ok();
+
+            if transferred {
+                env.events().publish(
+                    (Symbol::new(&env, "recipient_paid"), distribution_id),
+                    receipt.clone(),
+                );
+                summary.paid_amount += amount;
+                summary.paid.push_back(receipt);
+            } else {
+                Self::credit_pending(&env, distribution_id, &token, &receipt);
+                summary.pending_amount += amount;
+                summary.pending.push_back(receipt);
+            }
+        }
+
+        summary
+    }
+
+    /// Pull a pending balance left by a resilient distribution
+    pub fn claim_pending(env: Env, token: Address, recipient: Address) -> i128 {
+        recipient.require_auth();
+
+        let key = (
+            Symbol::new(&env, "pending"),
+            token.clone(),
+            recipient.clone(),
+        );
+        let amount: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+        if amount <= 0 {
+            panic_with_error!(&env, Error::NothingToClaim);
+        }
+        env.storage().persistent().remove(&key);
+
+        token::Client::new(&env, &token).transfer(
+            &env.current_contract_address(),
+            &recipient,
+            &amount,
+        );
+
+        env.events().publish(
+            (Symbol::new(&env, "pending_claimed"), token),
+            Receipt { recipient, amount },
+        );
+
+        amount
+    }
+
+    /// Add an amount that could not be transferred to the recipient's pending balance
+    fn credit_pending(env: &Env, distribution_id: u64, token: &Address, receipt: &Receipt) {
+        let key = (
+            Symbol::new(env, "pending"),
+            token.clone(),
+            receipt.recipient.clone(),
+        );
+        let pending: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+        env.storage()
+            .persistent()
+            .set(&key, &(pending + receipt.amount));
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        env.events().publish(
+            (Symbol::new(env, "recipient_pending"), distribution_id),
+            receipt.clone(),
+        );
+    }
+
+    /// Get the pending balance of `recipient` in `token`
+    pub fn get_pending(env: Env, token: Address, recipient: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "pending"), token, recipient))
+            .unwrap_or(0)
+    }
+
+    /// Distribute a total amount by basis-point shares summing to 10000
+    /// Amounts are rounded on cumulative shares, so the whole total is paid out
+    /// and each recipient is within one unit of their exact share
+    pub fn distribute_percentage(
+        env: Env,
+        sender: Address,
+        token: Address,
+        total_amount: i128,
+        recipients: Vec<Address>,
+        bps: Vec<u32>,
+    ) -> Vec<Receipt> {
+        sender.require_auth();
+
+        if recipients.len() != bps.len() {
+            panic_with_error!(&env, Error::LengthMismatch);
+        }
+        if recipients.is_empty() {
+            panic_with_error!(&env, Error::NoRecipients);
+        }
+        if total_amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        let mut amounts = Vec::new(&env);
+        let mut cumulative_bps: u32 = 0;
+        let mut paid: i128 = 0;
+        for share in bps.iter() {
+            if share == 0 {
+                panic_with_error!(&env, Error::InvalidShares);
+            }
+            cumulative_bps = cumulative_bps.saturating_add(share);
+            if cumulative_bps > BPS_DENOMINATOR {
+                panic_with_error!(&env, Error::InvalidShares);
+            }
+
+            let cumulative_amount = Self::bps_of(total_amount, cumulative_bps);
+            amounts.push_back(cumulative_amount - paid);
+            paid = cumulative_amount;
+        }
+        if cumulative_bps != BPS_DENOMINATOR {
+            panic_with_error!(&env, Error::InvalidShares);
+        }
+
+        let quote = Self::build_quote(&env, &recipients, &amounts, 0, total_amount);
+        let distribution_id = Self::record_distribution(
+            &env,
+            &sender,
+            &token,
+            DistributionMode::Percentage,
+            quote.total_amount,
+            quote.fee_amount,
+            recipients.len(),
+        );
+
+        Self::transfer_all(
+            &env,
+            distribution_id,
+            &sender,
+            &token,
+            &quote.receipts,
+            quote.fee_amount,
+        )
+    }
+
+    /// Distribute amounts as linear vesting streams sharing the same schedule
+    /// Each recipient gets a fully funded stream in the payment-stream contract,
+    /// with the sender as the stream's sender; no distribution fee is taken
+    /// since the payment-stream contract charges its own on withdrawal
+    pub fn distribute_as_streams(
+        env: Env,
+        sender: Address,
+        token: Address,
+        recipients: Vec<Address>,
+        amounts: Vec<i128>,
+        start_time: u64,
+        end_time: u64,
+    ) -> Vec<u64> {
+        sender.require_auth();
+
+        if end_time <= start_time {
+            panic_with_error!(&env, Error::InvalidTimeRange);
+        }
+        let total_amount = Self::validate_amounts(&env, &recipients, &amounts);
+
+        let payment_stream: Address = env
+            .storage()
+            .instance()
+            .get(&Symbol::new(&env, "payment_stream"))
+            .unwrap_or_else(|| panic_with_error!(&env, Error::NotInitialized));
+        let stream_client = PaymentStreamClient::new(&env, &payment_stream);
+
+        let distribution_id = Self::record_distribution(
+            &env,
+            &sender,
+            &token,
+            DistributionMode::Streams,
+            total_amount,
+            0,
+            recipients.len(),
+        );
+
+        let mut stream_ids = Vec::new(&env);
+        for (recipient, amount) in recipients.iter().zip(amounts.iter()) {
+            let stream_id = stream_client.create_stream(
+                &sender,
+                &recipient,
+                &token,
+                &amount,
+                &amount,
+                &start_time,
+                &end_time,
+            );
+
+            env.events().publish(
+                (Symbol::new(&env, "stream_distributed"), distribution_id),
+                StreamReceipt {
+                    recipient,
+                    amount,
+                    stream_id,
+                },
+            );
+            stream_ids.push_back(stream_id);
+        }
+
+        stream_ids
+    }
+
+    /// Escrow funds for allocations that each recipient pulls with `claim_allocation`
+    /// Unclaimed allocations can be reclaimed by the sender after `deadline`
+    pub fn create_allocations(
+        env: Env,
+        sender: Address,
+        token: Address,
+        recipients: Vec<Address>,
+        amounts: Vec<i128>,
+        deadline: u64,
+    ) -> u64 {
+        sender.require_auth();
+
+        if deadline <= env.ledger().timestamp() {
+            panic_with_error!(&env, Error::InvalidDeadline);
+        }
+        let total_amount = Self::validate_amounts(&env, &recipients, &amounts);
+        let (amounts, fee_amount) = Self::deduct_fees(&env, &amounts);
+        let escrowed = total_amount - fee_amount;
+
+        token::Client::new(&env, &token).transfer(
+            &sender,
+            &env.current_contract_address(),
+            &escrowed,
+        );
+
+        let distribution_id = Self::record_distribution(
+            &env,
+            &sender,
+            &token,
+            DistributionMode::Allocation,
+            total_amount,
+            fee_amount,
+            recipients.len(),
+        );
+        Self::pay_fee(&env, distribution_id, &sender, &token, fee_amount);
+
+        let ttl = Self::allocation_ttl(&env, deadline);
+        for (recipient, amount) in recipients.iter().zip(amounts.iter()) {
+            let key = (
+                Symbol::new(&env, "allocation"),
+                distribution_id,
+                recipient.clone(),
+            );
+            let allocated: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+            env.storage().persistent().set(&key, &(allocated + amount));
+            env.storage()
+                .persistent()
+                .extend_ttl(&key, LEDGER_THRESHOLD, ttl);
+
+            env.events().publish(
+                (Symbol::new(&env, "allocation"), distribution_id),
+                Receipt { recipient, amount },
+            );
+        }
+
+        let pool = AllocationPool {
+            distribution_id,
+            sender,
+            token,
+            total_amount: escrowed,
+            claimed_amount: 0,
+            deadline,
+            reclaimed: false,
+        };
+        Self::save_allocation_pool(&env, &pool);
+
+        distribution_id
+    }
+
+    /// Pull the caller's allocation from a distribution before its deadline
+    pub fn claim_allocation(env: Env, distribution_id: u64, recipient: Address) -> i128 {
+        recipient.require_auth();
+
+        let mut pool = Self::get_allocation_pool(env.clone(), distribution_id);
+        if env.ledger().timestamp() >= pool.deadline {
+            panic_with_error!(&env, Error::Expired);
+        }
+
+        let key = (
+            Symbol::new(&env, "allocation"),
+            distribution_id,
+            recipient.clone(),
+        );
+        let amount: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+        if amount <= 0 {
+            panic_with_error!(&env, Error::NothingToClaim);
+        }
+        env.storage().persistent().remove(&key);
+
+        pool.claimed_amount += amount;
+        Self::save_allocation_pool(&env, &pool);
+
+        token::Client::new(&env, &pool.token).transfer(
+            &env.current_contract_address(),
+            &recipient,
+            &amount,
+        );
+
+        env.events().publish(
+            (Symbol::new(&env, "allocation_claimed"), distribution_id),
+            Receipt { recipient, amount },
+        );
+
+        amount
+    }
+
+    /// Return unclaimed allocations to the sender once the deadline has passed
+    pub fn reclaim_expired(env: Env, distribution_id: u64) -> i128 {
+        let mut pool = Self::get_allocation_pool(env.clone(), distribution_id);
+        pool.sender.require_auth();
+
+        if env.ledger().timestamp() < pool.deadline {
+            panic_with_error!(&env, Error::NotExpired);
+        }
+        if pool.reclaimed {
+            panic_with_error!(&env, Error::AlreadyReclaimed);
+        }
+
+        let unclaimed = pool.total_amount - pool.claimed_amount;
+        pool.reclaimed = true;
+        Self::save_allocation_pool(&env, &pool);
+
+        if unclaimed > 0 {
+            token::Client::new(&env, &pool.token).transfer(
+                &env.current_contract_address(),
+                &pool.sender,
+                &unclaimed,
+            );
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "allocations_reclaimed"), distribution_id),
+            unclaimed,
+        );
+
+        unclaimed
+    }
+
+    /// Get the unclaimed allocation of `recipient` in a distribution
+    pub fn get_allocation(env: Env, distribution_id: u64, recipient: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "allocation"), distribution_id, recipient))
+            .unwrap_or(0)
+    }
+
+    /// Get the escrow backing a pull-based distribution
+    pub fn get_allocation_pool(env: Env, distribution_id: u64) -> AllocationPool {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "alloc_pool"), distribution_id))
+            .unwrap_or_else(|| panic_with_error!(&env, Error::AllocationsNotFound))
+    }
+
+    /// Escrow `total_amount` for a distribution too large for one transaction
+    /// Recipients are added with `append_recipients` and paid with `execute_job`
+    pub fn create_batch_job(env: Env, sender: Address, token: Address, total_amount: i128) -> u64 {
+        sender.require_auth();
+
+        if total_amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        let token_client = token::Client::new(&env, &token);
+        let held_before = token_client.balance(&env.current_contract_address());
+        token_client.transfer(&sender, &env.current_contract_address(), &total_amount);
+        let escrow_amount = token_client.balance(&env.current_contract_address()) - held_before;
+
+        let count_key = Symbol::new(&env, "job_count");
+        let job_id: u64 = env.storage().instance().get(&count_key).unwrap_or(0) + 1;
+        env.storage().instance().set(&count_key, &job_id);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        let job = BatchJob {
+            id: job_id,
+            sender,
+            token,
+            total_amount,
+            allocated_amount: 0,
+            fee_amount: 0,
+            paid_amount: 0,
+            pending_amount: 0,
+            escrow_amount,
+            recipient_count: 0,
+            cursor: 0,
+            distribution_id: 0,
+            status: BatchStatus::Open,
+        };
+        Self::save_batch_job(&env, &job);
+
+        job_id
+    }
+
+    /// Add recipients to an open batch job
+    /// The distribution fee is taken at the rate in force when they are appended
+    pub fn append_recipients(env: Env, job_id: u64, recipients: Vec<Address>, amounts: Vec<i128>) {
+        let mut job = Self::get_batch_job(env.clone(), job_id);
+        job.sender.require_auth();
+
+        if job.status != BatchStatus::Open {
+            panic_with_error!(&env, Error::JobNotOpen);
+        }
+
+        let appended = Self::validate_amounts(&env, &recipients, &amounts);
+        let (amounts, fee_amount) = Self::deduct_fees(&env, &amounts);
+        job.fee_amount += fee_amount;
+        job.allocated_amount = job
+            .allocated_amount
+            .checked_add(appended)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::ArithmeticOverflow));
+        if job.allocated_amount > job.total_amount {
+            panic_with_error!(&env, Error::ExceedsEscrow);
+        }
+
+        for (recipient, amount) in recipients.iter().zip(amounts.iter()) {
+            let key = (Symbol::new(&env, "job_entry"), job_id, job.recipient_count);
+            env.storage()
+                .persistent()
+                .set(&key, &Receipt { recipient, amount });
+            env.storage()
+                .persistent()
+                .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+            job.recipient_count += 1;
+        }
+
+        Self::save_batch_job(&env, &job);
+    }
+
+    /// Pay up to `max_count` recipients of a batch job from its cursor
+    /// The first call freezes the recipient list; the call that reaches the end
+    /// reconciles the job and refunds any unallocated escrow to the sender.
+    /// Amounts that cannot be transferred become pending balances, so one
+    /// frozen recipient cannot stall the job.
+    pub fn execute_job(env: Env, job_id: u64, max_count: u32) -> u32 {
+        let mut job = Self::get_batch_job(env.clone(), job_id);
+        job.sender.require_auth();
+
+        if job.status == BatchStatus::Completed {
+            panic_with_error!(&env, Error::JobCompleted);
+        }
+
+        if job.status == BatchStatus::Open {
+            job.distribution_id = Self::record_distribution(
+                &env,
+                &job.sender,
+                &job.token,
+                DistributionMode::Batch,
+                job.allocated_amount,
+                job.fee_amount,
+                job.recipient_count,
+            );
+            job.status = BatchStatus::Executing;
+        }
+
+        let token_client = token::Client::new(&env, &job.token);
+        let end = job
+            .cursor
+            .saturating_add(max_count)
+            .min(job.recipient_count);
+        let processed = end - job.cursor;
+        let held_before = token_client.balance(&env.current_contract_address());
+        let mut pending_amount = 0;
+
+        for index in job.cursor..end {
+            let key = (Symbol::new(&env, "job_entry"), job_id, index);
+            let receipt: Receipt = env.storage().persistent().get(&key).unwrap();
+            env.storage().persistent().remove(&key);
+
+            let transferred = token_client
+                .try_transfer(
+                    &env.current_contract_address(),
+                    &receipt.recipient,
+                    &receipt.amount,
+                )
+                .is_ok();
+
+            if transferred {
+                job.paid_amount += receipt.amount;
+                env.events().publish(
+                    (Symbol::new(&env, "recipient_paid"), job.distribution_id),
+                    receipt,
+                );
+            } else {
+                pending_amount += receipt.amount;
+                Self::credit_pending(&env, job.distribution_id, &job.token, &receipt);
+            }
+        }
+        job.cursor = end;
+        job.pending_amount += pending_amount;
+        // Pending balances stay in the contract but no longer belong to the job
+        job.escrow_amount -=
+            held_before - token_client.balance(&env.current_contract_address()) + pending_amount;
+
+        if job.cursor == job.recipient_count {
+            // What is left must cover exactly the fee and the unallocated refund
+            if job.escrow_amount != job.fee_amount + job.total_amount - job.allocated_amount {
+                panic_with_error!(&env, Error::ReconciliationFailed);
+            }
+
+            Self::pay_fee(
+                &env,
+                job.distribution_id,
+                &env.current_contract_address(),
+                &job.token,
+                job.fee_amount,
+            );
+
+            let unallocated = job.total_amount - job.allocated_amount;
+            if unallocated > 0 {
+                token_client.transfer(&env.current_contract_address(), &job.sender, &unallocated);
+            }
+            job.escrow_amount = 0;
+            job.status = BatchStatus::Completed;
+
+            env.events().publish(
+                (Symbol::new(&env, "job_completed"), job_id),
+                (job.paid_amount, unallocated),
+            );
+        }
+
+        Self::save_batch_job(&env, &job);
+
+        processed
+    }
+
+    /// Get a batch job by id
+    pub fn get_batch_job(env: Env, job_id: u64) -> BatchJob {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "job"), job_id))
+            .unwrap_or_else(|| panic_with_error!(&env, Error::JobNotFound))
+    }
+
+    /// Get a distribution record by id
+    pub fn get_distribution(env: Env, distribution_id: u64) -> Distribution {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "distribution"), distribution_id))
+            .unwrap_or_else(|| panic_with_error!(&env, Error::DistributionNotFound))
+    }
+
+    /// Get up to `limit` distributions made by `sender`, oldest first, skipping the first `start`
+    pub fn get_distributions_by_sender(
+        env: Env,
+        sender: Address,
+        start: u32,
+        limit: u32,
+    ) -> Vec<Distribution> {
+        let count: u32 = env
+            .storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "sender_count"), sender.clone()))
+            .unwrap_or(0);
+        let end = start.saturating_add(limit).min(count);
+
+        let mut distributions = Vec::new(&env);
+        for i in start..end {
+            let distribution_id: u64 = env
+                .storage()
+                .persistent()
+                .get(&(Symbol::new(&env, "sender_dist"), sender.clone(), i))
+                .unwrap();
+            distributions.push_back(Self::get_distribution(env.clone(), distribution_id));
+        }
+
+        distributions
+    }
+
+    /// Get the payment-stream contract used for stream distributions
+    pub fn get_payment_stream(env: Env) -> Option<Address> {
+        env.storage()
+            .instance()
+            .get(&Symbol::new(&env, "payment_stream"))
+    }
+
+    /// Get the admin address
+    pub fn get_admin(env: Env) -> Option<Address> {
+        env.storage().instance().get(&Symbol::new(&env, "admin"))
+    }
+
+    /// Create a Merkle airdrop campaign funded with `total_amount` from the creator
+    /// Leaves are built with `campaign_leaf` and paired with `campaign_node`
+    pub fn create_campaign(
+        env: Env,
+        creator: Address,
+        token: Address,
+        total_amount: i128,
+        merkle_root: BytesN<32>,
+        expiry: u64,
+    ) -> u64 {
+        creator.require_auth();
+
+        if total_amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+        if expiry <= env.ledger().timestamp() {
+            panic_with_error!(&env, Error::InvalidDeadline);
+        }
+
+        token::Client::new(&env, &token).transfer(
+            &creator,
+            &env.current_contract_address(),
+            &total_amount,
+        );
+
+        let count_key = Symbol::new(&env, "campaign_count");
+        let campaign_id: u64 = env.storage().instance().get(&count_key).unwrap_or(0) + 1;
+        env.storage().instance().set(&count_key, &campaign_id);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        let campaign = Campaign {
+            id: campaign_id,
+            creator: creator.clone(),
+            token,
+            merkle_root,
+            total_amount,
+            claimed_amount: 0,
+            expiry,
+            reclaimed: false,
+        };
+        Self::save_campaign(&env, &campaign);
+
+        env.events()
+            .publish((Symbol::new(&env, "campaign_created"), creator), campaign);
+
+        campaign_id
+    }
+
+    /// Claim the `amount` allocated to `recipient` at leaf `index` of a campaign
+    pub fn claim(
+        env: Env,
+        campaign_id: u64,
+        index: u32,
+        recipient: Address,
+        amount: i128,
+        proof: Vec<BytesN<32>>,
+    ) {
+        recipient.require_auth();
+
+        let mut campaign = Self::get_campaign(env.clone(), campaign_id);
+        if env.ledger().timestamp() >= campaign.expiry {
+            panic_with_error!(&env, Error::Expired);
+        }
+        if Self::is_claimed(env.clone(), campaign_id, index) {
+            panic_with_error!(&env, Error::AlreadyClaimed);
+        }
+
+        let mut node = campaign_leaf(&env, index, &recipient, amount);
+        for sibling in proof.iter() {
+            node = campaign_node(&env, &node, &sibling);
+        }
+        if node != campaign.merkle_root {
+            panic_with_error!(&env, Error::InvalidProof);
+        }
+
+        campaign.claimed_amount = campaign
+            .claimed_amount
+            .checked_add(amount)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::ArithmeticOverflow));
+        if campaign.claimed_amount > campaign.total_amount {
+            panic_with_error!(&env, Error::CampaignUnderfunded);
+        }
+        Self::save_campaign(&env, &campaign);
+
+        let word_key = (Symbol::new(&env, "claimed"), campaign_id, index / 128);
+        let word: u128 = env.storage().persistent().get(&word_key).unwrap_or(0);
+        env.storage()
+            .persistent()
+            .set(&word_key, &(word | (1u128 << (index % 128))));
+        env.storage()
+            .persistent()
+            .extend_ttl(&word_key, LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        token::Client::new(&env, &campaign.token).transfer(
+            &env.current_contract_address(),
+            &recipient,
+            &amount,
+        );
+
+        env.events().publish(
+            (Symbol::new(&env, "campaign_claimed"), campaign_id),
+            Receipt { recipient, amount },
+        );
+    }
+
+    /// Return unclaimed tokens to the creator once a campaign has expired
+    pub fn reclaim_campaign(env: Env, campaign_id: u64) -> i128 {
+        let mut campaign = Self::get_campaign(env.clone(), campaign_id);
+        campaign.creator.require_auth();
+
+        if env.ledger().timestamp() < campaign.expiry {
+            panic_with_error!(&env, Error::NotExpired);
+        }
+        if campaign.reclaimed {
+            panic_with_error!(&env, Error::AlreadyReclaimed);
+        }
+
+        let unclaimed = campaign.total_amount - campaign.claimed_amount;
+        campaign.reclaimed = true;
+        Self::save_campaign(&env, &campaign);
+
+        if unclaimed > 0 {
+            token::Client::new(&env, &campaign.token).transfer(
+                &env.current_contract_address(),
+                &campaign.creator,
+                &unclaimed,
+            );
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "campaign_reclaimed"), campaign_id),
+            unclaimed,
+        );
+
+        unclaimed
+    }
+
+    /// Get a campaign by id
+    pub fn get_campaign(env: Env, campaign_id: u64) -> Campaign {
+        env.storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "campaign"), campaign_id))
+            .unwrap_or_else(|| panic_with_error!(&env, Error::CampaignNotFound))
+    }
+
+    /// Check whether the leaf at `index` of a campaign has been claimed
+    pub fn is_claimed(env: Env, campaign_id: u64, index: u32) -> bool {
+        let word: u128 = env
+            .storage()
+            .persistent()
+            .get(&(Symbol::new(&env, "claimed"), campaign_id, index / 128))
+            .unwrap_or(0);
+        word & (1u128 << (index % 128)) != 0
+    }
+
+    /// Number of ledgers to keep allocation entries alive: the time left until the deadline plus the usual bump
+    fn allocation_ttl(env: &Env, deadline: u64) -> u32 {
+        let remaining = deadline.saturating_sub(env.ledger().timestamp());
+        let ledgers = (remaining / LEDGER_SECONDS).min(u32::MAX as u64) as u32;
+        ledgers
+            .saturating_add(LEDGER_BUMP)
+            .min(env.storage().max_ttl())
+    }
+
+    fn save_schedule(env: &Env, schedule: &Schedule) {
+        let key = (Symbol::new(env, "schedule"), schedule.id);
+        env.storage().persistent().set(&key, schedule);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    fn save_group(env: &Env, group: &RecipientGroup) {
+        let key = (Symbol::new(env, "group"), group.id);
+        env.storage().persistent().set(&key, group);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    fn require_group_editor(env: &Env, group: &RecipientGroup, caller: &Address) {
+        caller.require_auth();
+        if *caller != group.owner && !group.managers.contains(caller) {
+            panic_with_error!(env, Error::Unauthorized);
+        }
+    }
+
+    /// Set the weight of each member, adding those not yet in the group
+    fn upsert_members(
+        env: &Env,
+        group: &mut RecipientGroup,
+        caller: &Address,
+        members: &Vec<Address>,
+        weights: &Vec<u32>,
+    ) {
+        if members.len() != weights.len() {
+            panic_with_error!(env, Error::LengthMismatch);
+        }
+
+        for (member, weight) in members.iter().zip(weights.iter()) {
+            if weight == 0 {
+                panic_with_error!(env, Error::InvalidShares);
+            }
+            match group.members.first_index_of(&member) {
+                Some(index) => group.weights.set(index, weight),
+                None => {
+                    group.members.push_back(member.clone());
+                    group.weights.push_back(weight);
+                }
+            }
+
+            env.events().publish(
+                (Symbol::new(env, "group_updated"), group.id),
+                (caller.clone(), member, weight),
+            );
+        }
+    }
+
+    fn save_batch_job(env: &Env, job: &BatchJob) {
+        let key = (Symbol::new(env, "job"), job.id);
+        env.storage().persistent().set(&key, job);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    fn save_allocation_pool(env: &Env, pool: &AllocationPool) {
+        let key = (Symbol::new(env, "alloc_pool"), pool.distribution_id);
+        env.storage().persistent().set(&key, pool);
+        env.storage().persistent().extend_ttl(
+            &key,
+            LEDGER_THRESHOLD,
+            Self::allocation_ttl(env, pool.deadline),
+        );
+    }
+
+    fn save_campaign(env: &Env, campaign: &Campaign) {
+        let key = (Symbol::new(env, "campaign"), campaign.id);
+        env.storage().persistent().set(&key, campaign);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    /// Check recipients and amounts line up and every amount is positive, returning the total
+    fn validate_amounts(env: &Env, recipients: &Vec<Address>, amounts: &Vec<i128>) -> i128 {
+        if recipients.len() != amounts.len() {
+            panic_with_error!(env, Error::LengthMismatch);
+        }
+        if recipients.is_empty() {
+            panic_with_error!(env, Error::NoRecipients);
+        }
+
+        let mut total_amount: i128 = 0;
+        for amount in amounts.iter() {
+            if amount <= 0 {
+                panic_with_error!(env, Error::InvalidAmount);
+            }
+            total_amount = total_amount
+                .checked_add(amount)
+                .unwrap_or_else(|| panic_with_error!(env, Error::ArithmeticOverflow));
+        }
+
+        total_amount
+    }
+
+    /// Store a distribution record, index it under the sender and emit a summary event
+    fn record_distribution(
+        env: &Env,
+        sender: &Address,
+        token: &Address,
+        mode: DistributionMode,
+        total_amount: i128,
+        fee_amount: i128,
+        recipient_count: u32,
+    ) -> u64 {
+        let count_key = Symbol::new(env, "distribution_count");
+        let distribution_id: u64 = env.storage().instance().get(&count_key).unwrap_or(0) + 1;
+        env.storage().instance().set(&count_key, &distribution_id);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        let distribution = Distribution {
+            id: distribution_id,
+            sender: sender.clone(),
+            token: token.clone(),
+            mode,
+            total_amount,
+            fee_amount,
+            recipient_count,
+            timestamp: env.ledger().timestamp(),
+        };
+        let record_key = (Symbol::new(env, "distribution"), distribution_id);
+        env.storage().persistent().set(&record_key, &distribution);
+        env.storage()
+            .persistent()
+            .extend_ttl(&record_key, LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        let sender_count_key = (Symbol::new(env, "sender_count"), sender.clone());
+        let sender_count: u32 = env
+            .storage()
+            .persistent()
+            .get(&sender_count_key)
+            .unwrap_or(0);
+        let index_key = (
+            Symbol::new(env, "sender_dist"),
+            sender.clone(),
+            sender_count,
+        );
+        env.storage().persistent().set(&index_key, &distribution_id);
+        env.storage()
+            .persistent()
+            .extend_ttl(&index_key, LEDGER_THRESHOLD, LEDGER_BUMP);
+        env.storage()
+            .persistent()
+            .set(&sender_count_key, &(sender_count + 1));
+        env.storage()
+            .persistent()
+            .extend_ttl(&sender_count_key, LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        env.events().publish(
+            (Symbol::new(env, "distribution"), sender.clone()),
+            distribution,
+        );
+
+        distribution_id
+    }
+
+    /// Floor of `amount * bps / 10000`
+    fn bps_of(amount: i128, bps: u32) -> i128 {
+        Self::share_of(amount, bps, BPS_DENOMINATOR)
+    }
+
+    /// Floor of `amount * part / whole`, split to avoid overflow
+    fn share_of(amount: i128, part: u32, whole: u32) -> i128 {
+        let denominator = whole as i128;
+        let rate = part as i128;
+        (amount / denominator) * rate + ((amount % denominator) * rate) / denominator
+    }
+
+    /// Pay each receipt from `sender` (the contract itself for escrowed funds), then the fee to the collector
+    fn transfer_all(
+        env: &Env,
+        distribution_id: u64,
+        sender: &Address,
+        token: &Address,
+        receipts: &Vec<Receipt>,
+        fee_amount: i128,
+    ) -> Vec<Receipt> {
+        let token_client = token::Client::new(env, token);
+
+        for receipt in receipts.iter() {
+            token_client.transfer(sender, &receipt.recipient, &receipt.amount);
+            env.events().publish(
+                (Symbol::new(env, "recipient_paid"), distribution_id),
+                receipt,
+            );
+        }
+        Self::pay_fee(env, distribution_id, sender, token, fee_amount);
+
+        receipts.clone()
+    }
+
+    /// Apply the fee to gross per-recipient amounts; shared by quotes and real distributions
+    fn build_quote(
+        env: &Env,
+        recipients: &Vec<Address>,
+        amounts: &Vec<i128>,
+        remainder: i128,
+        total_amount: i128,
+    ) -> Quote {
+        let (net_amounts, fee_amount) = Self::deduct_fees(env, amounts);
+
+        let mut receipts = Vec::new(env);
+        for (recipient, amount) in recipients.iter().zip(net_amounts.iter()) {
+            receipts.push_back(Receipt { recipient, amount });
+        }
+
+        Quote {
+            receipts,
+            fee_amount,
+            remainder,
+            total_amount,
+        }
+    }
+
+    /// Split the fee off each amount at the current rate, returning the net amounts and total fee
+    fn deduct_fees(env: &Env, amounts: &Vec<i128>) -> (Vec<i128>, i128) {
+        Self::deduct_fees_at(env, amounts, Self::get_fee_rate(env.clone()))
+    }
+
+    /// Split the fee off each amount at `fee_rate`, returning the net amounts and total fee
+    fn deduct_fees_at(env: &Env, amounts: &Vec<i128>, fee_rate: u32) -> (Vec<i128>, i128) {
+        let mut net_amounts = Vec::new(env);
+        let mut fee_amount: i128 = 0;
+
+        for amount in amounts.iter() {
+            let fee = Self::bps_of(amount, fee_rate);
+            net_amounts.push_back(amount - fee);
+            fee_amount += fee;
+        }
+
+        (net_amounts, fee_amount)
+    }
+
+    /// Send a distribution's fee from `from` to the fee collector
+    fn pay_fee(env: &Env, distribution_id: u64, from: &Address, token: &Address, fee_amount: i128) {
+        if fee_amount == 0 {
+            return;
+        }
+
+        let fee_collector = Self::get_fee_collector(env.clone())
+            .unwrap_or_else(|| panic_with_error!(env, Error::NotInitialized));
+        token::Client::new(env, token).transfer(from, &fee_collector, &fee_amount);
+
+        env.events().publish(
+            (Symbol::new(env, "fee_collected"), distribution_id),
+            fee_amount,
+        );
+    }
+
+    fn require_admin(env: &Env) -> Address {
+        let admin = Self::get_admin(env.clone())
+            .unwrap_or_else(|| panic_with_error!(env, Error::NotInitialized));
+        admin.require_auth();
+        admin
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use soroban_sdk::testutils::storage::Persistent as _;
+    use soroban_sdk::testutils::{Address as _, IssuerFlags, Ledger};
+    use soroban_sdk::vec;
+
+    fn setup_token(env: &Env, holder: &Address, amount: i128) -> Address {
+        let token_admin = Address::generate(env);
+        let sac = env.register_stellar_asset_contract_v2(token_admin);
+        token::StellarAssetClient::new(env, &sac.address()).mint(holder, &amount);
+        sac.address()
+    }
+
+    #[test]
+    fn test_initialize() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let payment_stream = Address::generate(&env);
+        client.initialize(&admin, &payment_stream);
+
+        let stored_admin = client.get_admin();
+        assert_eq!(stored_admin, Some(admin));
+        assert_eq!(client.get_payment_stream(), Some(payment_stream));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #1)")]
+    fn test_initialize_twice() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        client.initialize(&Address::generate(&env), &Address::generate(&env));
+        client.initialize(&Address::generate(&env), &Address::generate(&env));
+    }
+
+    #[test]
+    fn test_distribution_fee() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let collector = Address::generate(&env);
+        client.initialize(&admin, &Address::generate(&env));
+        assert_eq!(client.get_fee_collector(), Some(admin));
+
+        client.set_fee_collector(&collector);
+        client.set_fee_rate(&100);
+        assert_eq!(client.get_fee_rate(), 100);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 2000);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        let receipts = client.distribute_weighted(
+            &sender,
+            &token,
+            &vec![&env, alice.clone(), bob.clone()],
+            &vec![&env, 1000, 550],
+        );
+        assert_eq!(receipts.get(0).unwrap().amount, 990);
+        assert_eq!(receipts.get(1).unwrap().amount, 545);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&alice), 990);
+        assert_eq!(token_client.balance(&bob), 545);
+        assert_eq!(token_client.balance(&collector), 15);
+        assert_eq!(token_client.balance(&sender), 450);
+
+        let distribution = client.get_distribution(&1);
+        assert_eq!(distribution.total_amount, 1550);
+        assert_eq!(distribution.fee_amount, 15);
+    }
+
+    #[test]
+    fn test_quote_matches_distribution() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+        client.initialize(&Address::generate(&env), &Address::generate(&env));
+        client.set_fee_rate(&250);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 5000);
+        let treasury = Address::generate(&env);
+        let recipients = vec![
+            &env,
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+        ];
+        let policy = RemainderPolicy::SendTo(treasury.clone());
+
+        let quote = client.quote_equal(&1000, &recipients, &policy);
+        assert_eq!(quote.remainder, 1);
+        assert_eq!(quote.fee_amount, 24);
+        assert_eq!(quote.total_amount, 1000);
+        assert_eq!(quote.receipts.get(0).unwrap().amount, 325);
+
+        let token_client = token::Client::new(&env, &token);
+        let receipts = client.distribute_equal(&sender, &token, &1000, &recipients, &policy);
+        assert_eq!(receipts, quote.receipts);
+        assert_eq!(token_client.balance(&treasury), 1);
+        assert_eq!(token_client.balance(&sender), 4000);
+
+        let amounts = vec![&env, 400, 1001];
+        let quote = client.quote_weighted(&recipients.slice(0..2), &amounts);
+        assert_eq!(quote.fee_amount, 35);
+        assert_eq!(quote.total_amount, 1401);
+
+        let receipts =
+            client.distribute_weighted(&sender, &token, &recipients.slice(0..2), &amounts);
+        assert_eq!(receipts, quote.receipts);
+        assert_eq!(token_client.balance(&sender), 4000 - quote.total_amount);
+    }
+
+    fn account(env: &Env, seed: u8) -> Address {
+        let mut xdr = Bytes::from_array(env, &ACCOUNT_XDR_PREFIX);
+        xdr.extend_from_array(&[seed; 32]);
+        Address::from_xdr(env, &xdr).unwrap()
+    }
+
+    #[test]
+    fn test_packed_recipients_round_trip() {
+        let env = Env::default();
+
+        let recipients = vec![&env, account(&env, 1), account(&env, 2), account(&env, 3)];
+        let amounts = vec![&env, 1, 300, i128::MAX];
+
+        let packed = encode_recipients(&env, &recipients, &amounts);
+        // 1 byte for 1, 2 bytes for 300, 19 bytes for i128::MAX
+        assert_eq!(packed.len(), 3 * 32 + 1 + 2 + 19);
+
+        let (decoded_recipients, decoded_amounts) = decode_recipients(&env, &packed);
+        assert_eq!(decoded_recipients, recipients);
+        assert_eq!(decoded_amounts, amounts);
+    }
+
+    #[test]
+    fn test_distribute_packed() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let sac = env.register_stellar_asset_contract_v2(Address::generate(&env));
+        let token = sac.address();
+        token::StellarAssetClient::new(&env, &token).mint(&sender, &1000);
+
+        // The issuer is the one account that can receive the asset without a trustline
+        let issuer = sac.issuer().address();
+        let recipients = vec![&env, issuer.clone(), issuer];
+        let amounts = vec![&env, 150, 200];
+
+        let packed = encode_recipients(&env, &recipients, &amounts);
+        let receipts = client.distribute_packed(&sender, &token, &packed);
+        assert_eq!(
+            receipts,
+            client.quote_weighted(&recipients, &amounts).receipts
+        );
+        assert_eq!(token::Client::new(&env, &token).balance(&sender), 650);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #29)")]
+    fn test_distribute_packed_truncated() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+
+        let packed = encode_recipients(&env, &vec![&env, account(&env, 7)], &vec![&env, 300]);
+        client.distribute_packed(&sender, &token, &packed.slice(..packed.len() - 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #29)")]
+    fn test_decode_non_canonical_varint() {
+        let env = Env::default();
+
+        let mut packed = Bytes::from_array(&env, &[9; 32]);
+        packed.extend_from_array(&[0x85, 0x00]);
+        decode_recipients(&env, &packed);
+    }
+
+    #[test]
+    fn test_recipient_groups() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let owner = Address::generate(&env);
+        let manager = Address::generate(&env);
+        let token = setup_token(&env, &owner, 10_000);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let carol = Address::generate(&env);
+
+        let group_id = client.create_group(
+            &owner,
+            &String::from_str(&env, "contributors"),
+            &vec![&env, alice.clone(), bob.clone()],
+            &vec![&env, 1, 2],
+        );
+
+        client.distribute_to_group(&group_id, &token, &900);
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&alice), 300);
+        assert_eq!(token_client.balance(&bob), 600);
+
+        client.add_group_manager(&group_id, &manager);
+        client.update_group(
+            &manager,
+            &group_id,
+            &vec![&env, bob.clone(), carol.clone()],
+            &vec![&env, 1, 1],
+        );
+        client.remove_member(&manager, &group_id, &alice);
+
+        let group = client.get_group(&group_id);
+        assert_eq!(group.members, vec![&env, bob.clone(), carol.clone()]);
+        assert_eq!(group.weights, vec![&env, 1, 1]);
+
+        let receipts = client.distribute_to_group(&group_id, &token, &101);
+        assert_eq!(receipts.get(0).unwrap().amount, 50);
+        assert_eq!(receipts.get(1).unwrap().amount, 51);
+        assert_eq!(token_client.balance(&owner), 10_000 - 900 - 101);
+
+        let distribution = client.get_distribution(&2);
+        assert_eq!(distribution.mode, DistributionMode::Group);
+        assert_eq!(distribution.sender, owner);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #32)")]
+    fn test_update_group_unauthorized() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let owner = Address::generate(&env);
+        let group_id = client.create_group(
+            &owner,
+            &String::from_str(&env, "contributors"),
+            &vec![&env, Address::generate(&env)],
+            &vec![&env, 1],
+        );
+
+        client.update_group(
+            &Address::generate(&env),
+            &group_id,
+            &vec![&env, Address::generate(&env)],
+            &vec![&env, 5],
+        );
+    }
+
+    #[test]
+    fn test_scheduled_distribution() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        env.ledger().set_timestamp(1000);
+        let schedule_id = client.create_schedule(
+            &sender,
+            &token,
+            &vec![&env, alice.clone(), bob.clone()],
+            &vec![&env, 100, 50],
+            &30,
+            &4,
+        );
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&sender), 400);
+        assert_eq!(token_client.balance(&contract_id), 600);
+
+        // Two periods have elapsed, so two releases are due and a third is not
+        env.ledger().set_timestamp(1065);
+        client.execute_due(&schedule_id);
+        client.execute_due(&schedule_id);
+        assert_eq!(
+            client.try_execute_due(&schedule_id),
+            Err(Ok(Error::NothingDue.into()))
+        );
+        assert_eq!(token_client.balance(&alice), 200);
+        assert_eq!(token_client.balance(&bob), 100);
+
+        assert_eq!(client.cancel_schedule(&schedule_id), 300);
+        assert_eq!(token_client.balance(&sender), 700);
+        assert_eq!(token_client.balance(&contract_id), 0);
+
+        env.ledger().set_timestamp(2000);
+        assert_eq!(
+            client.try_execute_due(&schedule_id),
+            Err(Ok(Error::ScheduleInactive.into()))
+        );
+    }
+
+    #[test]
+    fn test_scheduled_distribution_keeps_fee_rate_and_skips_frozen_recipient() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let collector = Address::generate(&env);
+        client.initialize(&Address::generate(&env), &Address::generate(&env));
+        client.set_fee_collector(&collector);
+        client.set_fee_rate(&100);
+
+        let sender = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let frozen = Address::generate(&env);
+
+        let sac = env.register_stellar_asset_contract_v2(Address::generate(&env));
+        sac.issuer().set_flag(IssuerFlags::RevocableFlag);
+        let token = sac.address();
+        let token_admin = token::StellarAssetClient::new(&env, &token);
+        token_admin.mint(&sender, &2000);
+
+        env.ledger().set_timestamp(1000);
+        let schedule_id = client.create_schedule(
+            &sender,
+            &token,
+            &vec![&env, alice.clone(), frozen.clone()],
+            &vec![&env, 500, 500],
+            &30,
+            &2,
+        );
+        assert_eq!(client.get_schedule(&schedule_id).fee_rate, 100);
+
+        // A later rate change does not apply to what is already escrowed
+        client.set_fee_rate(&500);
+        token_admin.set_authorized(&frozen, &false);
+
+        env.ledger().set_timestamp(1030);
+        let distribution_id = client.execute_due(&schedule_id);
+        assert_eq!(client.get_distribution(&distribution_id).fee_amount, 10);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&alice), 495);
+        assert_eq!(token_client.balance(&collector), 10);
+        assert_eq!(client.get_pending(&token, &frozen), 495);
+
+        // The frozen recipient does not stall the next release
+        env.ledger().set_timestamp(1060);
+        client.execute_due(&schedule_id);
+        assert_eq!(token_client.balance(&alice), 990);
+        assert_eq!(client.get_pending(&token, &frozen), 990);
+        assert_eq!(token_client.balance(&contract_id), 990);
+    }
+
+    #[test]
+    fn test_distribute_from_allowance() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let treasury = Address::generate(&env);
+        let operator = Address::generate(&env);
+        let token = setup_token(&env, &treasury, 1000);
+        let alice = Address::generate(&env);
+
+        let token_client = token::Client::new(&env, &token);
+        token_client.approve(&treasury, &contract_id, &500, &1000);
+        client.set_operator(&treasury, &operator, &true);
+        assert!(client.is_operator(&treasury, &operator));
+
+        client.distribute_from(
+            &operator,
+            &treasury,
+            &token,
+            &vec![&env, alice.clone()],
+            &vec![&env, 300],
+        );
+        assert_eq!(token_client.balance(&alice), 300);
+        assert_eq!(token_client.balance(&treasury), 700);
+        assert_eq!(token_client.allowance(&treasury, &contract_id), 200);
+        assert_eq!(client.get_distribution(&1).sender, treasury);
+
+        // The remaining allowance caps further spending
+        assert!(client
+            .try_distribute_from(
+                &operator,
+                &treasury,
+                &token,
+                &vec![&env, alice],
+                &vec![&env, 201],
+            )
+            .is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #32)")]
+    fn test_distribute_from_requires_operator() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let treasury = Address::generate(&env);
+        let token = setup_token(&env, &treasury, 1000);
+        token::Client::new(&env, &token).approve(&treasury, &contract_id, &500, &1000);
+
+        client.distribute_from(
+            &Address::generate(&env),
+            &treasury,
+            &token,
+            &vec![&env, Address::generate(&env)],
+            &vec![&env, 100],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #28)")]
+    fn test_fee_rate_capped() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        client.initialize(&Address::generate(&env), &Address::generate(&env));
+        client.set_fee_rate(&(MAX_FEE_RATE + 1));
+    }
+
+    #[test]
+    fn test_distribute_equal() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let recipients = vec![
+            &env,
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+        ];
+
+        let receipts =
+            client.distribute_equal(&sender, &token, &900, &recipients, &RemainderPolicy::Refund);
+        assert_eq!(receipts.len(), 3);
+
+        let token_client = token::Client::new(&env, &token);
+        for recipient in recipients.iter() {
+            assert_eq!(token_client.balance(&recipient), 300);
+        }
+        assert_eq!(token_client.balance(&sender), 100);
+        assert_eq!(
+            receipts.get(0).unwrap(),
+            Receipt {
+                recipient: recipients.get(0).unwrap(),
+                amount: 300,
+            }
+        );
+    }
+
+    #[test]
+    fn test_distribute_weighted() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        let receipts = client.distribute_weighted(
+            &sender,
+            &token,
+            &vec![&env, alice.clone(), bob.clone()],
+            &vec![&env, 100, 250],
+        );
+        assert_eq!(receipts.len(), 2);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&alice), 100);
+        assert_eq!(token_client.balance(&bob), 250);
+        assert_eq!(token_client.balance(&sender), 650);
+    }
+
+    #[test]
+    fn test_distribute_multi() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let stable = setup_token(&env, &sender, 1000);
+        let governance = setup_token(&env, &sender, 500);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        let ids = client.distribute_multi(
+            &sender,
+            &vec![
+                &env,
+                (
+                    stable.clone(),
+                    vec![&env, alice.clone(), bob.clone()],
+                    vec![&env, 300, 200],
+                ),
+                (
+                    governance.clone(),
+                    vec![&env, alice.clone()],
+                    vec![&env, 50],
+                ),
+            ],
+        );
+        assert_eq!(ids.len(), 2);
+
+        let stable_client = token::Client::new(&env, &stable);
+        let governance_client = token::Client::new(&env, &governance);
+        assert_eq!(stable_client.balance(&alice), 300);
+        assert_eq!(stable_client.balance(&bob), 200);
+        assert_eq!(governance_client.balance(&alice), 50);
+        assert_eq!(governance_client.balance(&sender), 450);
+
+        let second = client.get_distribution(&ids.get(1).unwrap());
+        assert_eq!(second.token, governance);
+        assert_eq!(second.total_amount, 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #11)")]
+    fn test_distribute_multi_insufficient_balance() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let stable = setup_token(&env, &sender, 1000);
+        let governance = setup_token(&env, &sender, 10);
+        let alice = Address::generate(&env);
+
+        client.distribute_multi(
+            &sender,
+            &vec![
+                &env,
+                (stable, vec![&env, alice.clone()], vec![&env, 100]),
+                (governance, vec![&env, alice], vec![&env, 50]),
+            ],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #3)")]
+    fn test_distribute_weighted_rejects_zero_amount() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+
+        client.distribute_weighted(
+            &sender,
+            &token,
+            &vec![&env, Address::generate(&env), Address::generate(&env)],
+            &vec![&env, 100, 0],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #6)")]
+    fn test_distribute_weighted_rejects_overflow() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+
+        client.distribute_weighted(
+            &sender,
+            &token,
+            &vec![&env, Address::generate(&env), Address::generate(&env)],
+            &vec![&env, i128::MAX, 1],
+        );
+    }
+
+    #[test]
+    fn test_distribute_equal_refunds_remainder() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let recipients = vec![
+            &env,
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+        ];
+
+        client.distribute_equal(
+            &sender,
+            &token,
+            &1000,
+            &recipients,
+            &RemainderPolicy::Refund,
+        );
+
+        let token_client = token::Client::new(&env, &token);
+        for recipient in recipients.iter() {
+            assert_eq!(token_client.balance(&recipient), 333);
+        }
+        // The remainder is never pulled from the sender
+        assert_eq!(token_client.balance(&sender), 1);
+    }
+
+    #[test]
+    fn test_distribute_equal_remainder_to_first_recipients() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1001);
+        let recipients = vec![
+            &env,
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+        ];
+
+        let receipts = client.distribute_equal(
+            &sender,
+            &token,
+            &1001,
+            &recipients,
+            &RemainderPolicy::FirstRecipients,
+        );
+
+        assert_eq!(receipts.get(0).unwrap().amount, 334);
+        assert_eq!(receipts.get(1).unwrap().amount, 334);
+        assert_eq!(receipts.get(2).unwrap().amount, 333);
+        assert_eq!(token::Client::new(&env, &token).balance(&sender), 0);
+    }
+
+    #[test]
+    fn test_distribute_equal_remainder_to_address() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let treasury = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let recipients = vec![&env, Address::generate(&env), Address::generate(&env)];
+
+        client.distribute_equal(
+            &sender,
+            &token,
+            &999,
+            &recipients,
+            &RemainderPolicy::SendTo(treasury.clone()),
+        );
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&treasury), 1);
+        assert_eq!(token_client.balance(&sender), 1);
+    }
+
+    fn campaign_fixture(env: &Env) -> (Vec<Address>, Vec<BytesN<32>>, BytesN<32>) {
+        let recipients = vec![
+            env,
+            Address::generate(env),
+            Address::generate(env),
+            Address::generate(env),
+        ];
+        let amounts = [100i128, 200, 300];
+
+        let mut leaves = Vec::new(env);
+        for (i, recipient) in recipients.iter().enumerate() {
+            leaves.push_back(campaign_leaf(env, i as u32, &recipient, amounts[i]));
+        }
+
+        // Three leaves: the odd one out is promoted as-is
+        let ab = campaign_node(env, &leaves.get(0).unwrap(), &leaves.get(1).unwrap());
+        let root = campaign_node(env, &ab, &leaves.get(2).unwrap());
+        (recipients, leaves, root)
+    }
+
+    #[test]
+    fn test_campaign_hashes_are_domain_separated() {
+        let env = Env::default();
+        let (recipients, leaves, _) = campaign_fixture(&env);
+
+        let mut leaf_data = Bytes::from_array(&env, &[0x00]);
+        leaf_data.extend_from_array(&0u32.to_be_bytes());
+        leaf_data.append(&recipients.get(0).unwrap().to_xdr(&env));
+        leaf_data.extend_from_array(&100i128.to_be_bytes());
+        assert_eq!(
+            leaves.get(0).unwrap(),
+            env.crypto().sha256(&leaf_data).to_bytes()
+        );
+
+        let (a, b) = (leaves.get(0).unwrap(), leaves.get(1).unwrap());
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        let mut node_data = Bytes::from_array(&env, &[0x01]);
+        node_data.extend_from_array(&left.to_array());
+        node_data.extend_from_array(&right.to_array());
+        assert_eq!(
+            campaign_node(&env, &leaves.get(0).unwrap(), &leaves.get(1).unwrap()),
+            env.crypto().sha256(&node_data).to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_campaign_claim() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let token = setup_token(&env, &creator, 600);
+        let (recipients, leaves, root) = campaign_fixture(&env);
+
+        let campaign_id = client.create_campaign(&creator, &token, &600, &root, &1000);
+
+        let bob = recipients.get(1).unwrap();
+        let proof = vec![&env, leaves.get(0).unwrap(), leaves.get(2).unwrap()];
+        client.claim(&campaign_id, &1, &bob, &200, &proof);
+
+        let carol = recipients.get(2).unwrap();
+        let ab = campaign_node(&env, &leaves.get(0).unwrap(), &leaves.get(1).unwrap());
+        client.claim(&campaign_id, &2, &carol, &300, &vec![&env, ab]);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&bob), 200);
+        assert_eq!(token_client.balance(&carol), 300);
+        assert!(client.is_claimed(&campaign_id, &1));
+        assert!(!client.is_claimed(&campaign_id, &0));
+        assert_eq!(client.get_campaign(&campaign_id).claimed_amount, 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #25)")]
+    fn test_campaign_double_claim() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let token = setup_token(&env, &creator, 600);
+        let (recipients, leaves, root) = campaign_fixture(&env);
+
+        let campaign_id = client.create_campaign(&creator, &token, &600, &root, &1000);
+
+        let ab = campaign_node(&env, &leaves.get(0).unwrap(), &leaves.get(1).unwrap());
+        let carol = recipients.get(2).unwrap();
+        client.claim(&campaign_id, &2, &carol, &300, &vec![&env, ab.clone()]);
+        client.claim(&campaign_id, &2, &carol, &300, &vec![&env, ab]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #26)")]
+    fn test_campaign_claim_wrong_amount() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let token = setup_token(&env, &creator, 600);
+        let (recipients, leaves, root) = campaign_fixture(&env);
+
+        let campaign_id = client.create_campaign(&creator, &token, &600, &root, &1000);
+
+        let ab = campaign_node(&env, &leaves.get(0).unwrap(), &leaves.get(1).unwrap());
+        client.claim(
+            &campaign_id,
+            &2,
+            &recipients.get(2).unwrap(),
+            &600,
+            &vec![&env, ab],
+        );
+    }
+
+    #[test]
+    fn test_campaign_reclaim_after_expiry() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let creator = Address::generate(&env);
+        let token = setup_token(&env, &creator, 600);
+        let (recipients, leaves, root) = campaign_fixture(&env);
+
+        let campaign_id = client.create_campaign(&creator, &token, &600, &root, &1000);
+
+        let ab = campaign_node(&env, &leaves.get(0).unwrap(), &leaves.get(1).unwrap());
+        client.claim(
+            &campaign_id,
+            &2,
+            &recipients.get(2).unwrap(),
+            &300,
+            &vec![&env, ab],
+        );
+
+        env.ledger().set_timestamp(1000);
+        assert_eq!(client.reclaim_campaign(&campaign_id), 300);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&creator), 300);
+        assert_eq!(token_client.balance(&contract_id), 0);
+        assert!(client.get_campaign(&campaign_id).reclaimed);
+    }
+
+    #[test]
+    fn test_distribute_percentage() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let recipients = vec![
+            &env,
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+        ];
+
+        // 33.33% / 33.33% / 33.34% of 1000
+        let receipts = client.distribute_percentage(
+            &sender,
+            &token,
+            &1000,
+            &recipients,
+            &vec![&env, 3333, 3333, 3334],
+        );
+
+        assert_eq!(receipts.get(0).unwrap().amount, 333);
+        assert_eq!(receipts.get(1).unwrap().amount, 333);
+        assert_eq!(receipts.get(2).unwrap().amount, 334);
+        assert_eq!(token::Client::new(&env, &token).balance(&sender), 0);
+    }
+
+    #[test]
+    fn test_distribute_percentage_pays_whole_total() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 101);
+        let recipients = vec![
+            &env,
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+        ];
+
+        let receipts = client.distribute_percentage(
+            &sender,
+            &token,
+            &101,
+            &recipients,
+            &vec![&env, 5000, 2500, 2500],
+        );
+
+        let total: i128 = receipts.iter().map(|receipt| receipt.amount).sum();
+        assert_eq!(total, 101);
+        assert_eq!(receipts.get(0).unwrap().amount, 50);
+        assert_eq!(receipts.get(1).unwrap().amount, 25);
+        assert_eq!(receipts.get(2).unwrap().amount, 26);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #8)")]
+    fn test_distribute_percentage_rejects_bad_total() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+
+        client.distribute_percentage(
+            &sender,
+            &token,
+            &1000,
+            &vec![&env, Address::generate(&env), Address::generate(&env)],
+            &vec![&env, 5000, 4000],
+        );
+    }
+
+    #[test]
+    fn test_distribute_as_streams() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let fee_collector = Address::generate(&env);
+        let stream_id = env.register(payment_stream::PaymentStreamContract, ());
+        let stream_client = payment_stream::PaymentStreamContractClient::new(&env, &stream_id);
+        stream_client.initialize(&admin, &fee_collector, &0);
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+        client.initialize(&admin, &stream_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        let stream_ids = client.distribute_as_streams(
+            &sender,
+            &token,
+            &vec![&env, alice.clone(), bob.clone()],
+            &vec![&env, 400, 600],
+            &100,
+            &200,
+        );
+        assert_eq!(stream_ids, vec![&env, 1, 2]);
+
+        let alice_stream = stream_client.get_stream(&1);
+        assert_eq!(alice_stream.sender, sender);
+        assert_eq!(alice_stream.recipient, alice);
+        assert_eq!(alice_stream.total_amount, 400);
+        assert_eq!(alice_stream.balance, 400);
+        assert_eq!(alice_stream.start_time, 100);
+        assert_eq!(alice_stream.end_time, 200);
+        assert_eq!(stream_client.get_stream(&2).recipient, bob);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&stream_id), 1000);
+        assert_eq!(token_client.balance(&sender), 0);
+    }
+
+    #[test]
+    fn test_distribution_history() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let other = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        token::StellarAssetClient::new(&env, &token).mint(&other, &1000);
+        let recipients = vec![&env, Address::generate(&env), Address::generate(&env)];
+
+        env.ledger().set_timestamp(10);
+        client.distribute_equal(&sender, &token, &301, &recipients, &RemainderPolicy::Refund);
+        client.distribute_weighted(&other, &token, &recipients, &vec![&env, 5, 6]);
+        env.ledger().set_timestamp(20);
+        client.distribute_percentage(&sender, &token, &100, &recipients, &vec![&env, 2500, 7500]);
+
+        assert_eq!(
+            client.get_distribution(&1),
+            Distribution {
+                id: 1,
+                sender: sender.clone(),
+                token: token.clone(),
+                mode: DistributionMode::Equal,
+                total_amount: 300,
+                fee_amount: 0,
+                recipient_count: 2,
+                timestamp: 10,
+            }
+        );
+
+        let history = client.get_distributions_by_sender(&sender, &0, &10);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(0).unwrap().id, 1);
+        assert_eq!(history.get(1).unwrap().id, 3);
+        assert_eq!(history.get(1).unwrap().mode, DistributionMode::Percentage);
+
+        let page = client.get_distributions_by_sender(&sender, &1, &1);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page.get(0).unwrap().id, 3);
+
+        assert_eq!(client.get_distributions_by_sender(&other, &0, &10).len(), 1);
+    }
+
+    #[test]
+    fn test_claim_allocation() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        let distribution_id = client.create_allocations(
+            &sender,
+            &token,
+            &vec![&env, alice.clone(), bob.clone()],
+            &vec![&env, 300, 200],
+            &100,
+        );
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&contract_id), 500);
+        assert_eq!(client.get_allocation(&distribution_id, &alice), 300);
+
+        assert_eq!(client.claim_allocation(&distribution_id, &alice), 300);
+        assert_eq!(token_client.balance(&alice), 300);
+        assert_eq!(client.get_allocation(&distribution_id, &alice), 0);
+        assert_eq!(
+            client.get_distribution(&distribution_id).mode,
+            DistributionMode::Allocation
+        );
+
+        // Bob never claims, so the sender takes it back after the deadline
+        env.ledger().set_timestamp(100);
+        assert_eq!(client.reclaim_expired(&distribution_id), 200);
+        assert_eq!(token_client.balance(&sender), 700);
+        assert_eq!(token_client.balance(&contract_id), 0);
+    }
+
+    #[test]
+    fn test_allocation_ttl_covers_deadline() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let alice = Address::generate(&env);
+
+        // A deadline a year out is well past the usual ~31 day bump
+        let deadline = 365 * 24 * 60 * 60;
+        let distribution_id = client.create_allocations(
+            &sender,
+            &token,
+            &vec![&env, alice.clone()],
+            &vec![&env, 300],
+            &deadline,
+        );
+
+        let min_ttl = (deadline / LEDGER_SECONDS) as u32;
+        env.as_contract(&contract_id, || {
+            let key = (Symbol::new(&env, "allocation"), distribution_id, alice);
+            assert!(env.storage().persistent().get_ttl(&key) > min_ttl);
+            let key = (Symbol::new(&env, "alloc_pool"), distribution_id);
+            assert!(env.storage().persistent().get_ttl(&key) > min_ttl);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #13)")]
+    fn test_claim_allocation_twice() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let alice = Address::generate(&env);
+
+        let distribution_id = client.create_allocations(
+            &sender,
+            &token,
+            &vec![&env, alice.clone()],
+            &vec![&env, 300],
+            &100,
+        );
+
+        client.claim_allocation(&distribution_id, &alice);
+        client.claim_allocation(&distribution_id, &alice);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #14)")]
+    fn test_claim_allocation_after_deadline() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let alice = Address::generate(&env);
+
+        let distribution_id = client.create_allocations(
+            &sender,
+            &token,
+            &vec![&env, alice.clone()],
+            &vec![&env, 300],
+            &100,
+        );
+
+        env.ledger().set_timestamp(100);
+        client.claim_allocation(&distribution_id, &alice);
+    }
+
+    #[test]
+    fn test_distribute_weighted_resilient() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let frozen = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        let sac = env.register_stellar_asset_contract_v2(Address::generate(&env));
+        sac.issuer().set_flag(IssuerFlags::RevocableFlag);
+        let token = sac.address();
+        let token_admin = token::StellarAssetClient::new(&env, &token);
+        token_admin.mint(&sender, &1000);
+
+        // A deauthorized balance makes transfers to `frozen` fail
+        token_admin.set_authorized(&frozen, &false);
+
+        let summary = client.distribute_weighted_resilient(
+            &sender,
+            &token,
+            &vec![&env, alice.clone(), frozen.clone(), bob.clone()],
+            &vec![&env, 100, 200, 300],
+        );
+
+        assert_eq!(summary.paid.len(), 2);
+        assert_eq!(summary.paid_amount, 400);
+        assert_eq!(
+            summary.pending,
+            vec![
+                &env,
+                Receipt {
+                    recipient: frozen.clone(),
+                    amount: 200,
+                }
+            ]
+        );
+        assert_eq!(summary.pending_amount, 200);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&alice), 100);
+        assert_eq!(token_client.balance(&bob), 300);
+        assert_eq!(token_client.balance(&contract_id), 200);
+        assert_eq!(client.get_pending(&token, &frozen), 200);
+
+        // Once the trustline is fixed the recipient pulls the pending balance
+        token_admin.set_authorized(&frozen, &true);
+        assert_eq!(client.claim_pending(&token, &frozen), 200);
+        assert_eq!(token_client.balance(&frozen), 200);
+        assert_eq!(client.get_pending(&token, &frozen), 0);
+    }
+
+    #[test]
+    fn test_batch_job_in_chunks() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+        let recipients = vec![
+            &env,
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+            Address::generate(&env),
+        ];
+
+        let job_id = client.create_batch_job(&sender, &token, &1000);
+        client.append_recipients(&job_id, &recipients.slice(0..3), &vec![&env, 100, 100, 100]);
+        client.append_recipients(&job_id, &recipients.slice(3..5), &vec![&env, 200, 250]);
+        assert_eq!(client.get_batch_job(&job_id).recipient_count, 5);
+
+        assert_eq!(client.execute_job(&job_id, &2), 2);
+        let job = client.get_batch_job(&job_id);
+        assert_eq!(job.cursor, 2);
+        assert_eq!(job.status, BatchStatus::Executing);
+
+        assert_eq!(client.execute_job(&job_id, &2), 2);
+        assert_eq!(client.execute_job(&job_id, &2), 1);
+
+        let job = client.get_batch_job(&job_id);
+        assert_eq!(job.status, BatchStatus::Completed);
+        assert_eq!(job.paid_amount, 750);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&recipients.get(4).unwrap()), 250);
+        // The unallocated 250 goes back to the sender on reconciliation
+        assert_eq!(token_client.balance(&sender), 250);
+        assert_eq!(token_client.balance(&contract_id), 0);
+
+        let distribution = client.get_distribution(&job.distribution_id);
+        assert_eq!(distribution.mode, DistributionMode::Batch);
+        assert_eq!(distribution.total_amount, 750);
+        assert_eq!(distribution.recipient_count, 5);
+    }
+
+    #[test]
+    fn test_batch_job_skips_frozen_recipient() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let frozen = Address::generate(&env);
+        let bob = Address::generate(&env);
+
+        let sac = env.register_stellar_asset_contract_v2(Address::generate(&env));
+        sac.issuer().set_flag(IssuerFlags::RevocableFlag);
+        let token = sac.address();
+        let token_admin = token::StellarAssetClient::new(&env, &token);
+        token_admin.mint(&sender, &1000);
+        token_admin.set_authorized(&frozen, &false);
+
+        let job_id = client.create_batch_job(&sender, &token, &1000);
+        client.append_recipients(
+            &job_id,
+            &vec![&env, alice.clone(), frozen.clone(), bob.clone()],
+            &vec![&env, 100, 200, 300],
+        );
+
+        assert_eq!(client.execute_job(&job_id, &2), 2);
+        assert_eq!(client.execute_job(&job_id, &2), 1);
+
+        let job = client.get_batch_job(&job_id);
+        assert_eq!(job.status, BatchStatus::Completed);
+        assert_eq!(job.paid_amount, 400);
+        assert_eq!(job.pending_amount, 200);
+        assert_eq!(job.escrow_amount, 0);
+
+        let token_client = token::Client::new(&env, &token);
+        assert_eq!(token_client.balance(&bob), 300);
+        assert_eq!(token_client.balance(&sender), 400);
+        // Only the pending balance stays behind
+        assert_eq!(token_client.balance(&contract_id), 200);
+        assert_eq!(client.get_pending(&token, &frozen), 200);
+
+        token_admin.set_authorized(&frozen, &true);
+        assert_eq!(client.claim_pending(&token, &frozen), 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #23)")]
+    fn test_batch_job_reconciles_against_held_escrow() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+
+        let job_id = client.create_batch_job(&sender, &token, &500);
+        client.append_recipients(
+            &job_id,
+            &vec![&env, Address::generate(&env)],
+            &vec![&env, 400],
+        );
+
+        // Escrow recorded for the job no longer matches what was taken in
+        env.as_contract(&contract_id, || {
+            let mut job = DistributorContract::get_batch_job(env.clone(), job_id);
+            job.escrow_amount -= 50;
+            DistributorContract::save_batch_job(&env, &job);
+        });
+
+        client.execute_job(&job_id, &1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #22)")]
+    fn test_batch_job_over_allocation() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+
+        let job_id = client.create_batch_job(&sender, &token, &100);
+        client.append_recipients(
+            &job_id,
+            &vec![&env, Address::generate(&env), Address::generate(&env)],
+            &vec![&env, 60, 60],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #20)")]
+    fn test_batch_job_append_after_execute() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let contract_id = env.register(DistributorContract, ());
+        let client = DistributorContractClient::new(&env, &contract_id);
+
+        let sender = Address::generate(&env);
+        let token = setup_token(&env, &sender, 1000);
+
+        let job_id = client.create_batch_job(&sender, &token, &100);
+        client.append_recipients(
+            &job_id,
+            &vec![&env, Address::generate(&env), Address::generate(&env)],
+            &vec![&env, 50, 50],
+        );
+        client.execute_job(&job_id, &1);
+        client.append_recipients(
+            &job_id,
+            &vec![&env, Address::generate(&env)],
+            &vec![&env, 1],
+        );
     }
 }
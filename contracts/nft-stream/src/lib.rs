@@ -1,8 +1,10 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype, token, Address, Env, Symbol,
+    contract, contracterror, contractimpl, contracttype, token, Address, Env, String, Symbol, Vec,
 };
 
+mod metadata;
+
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u32)]
@@ -17,8 +19,19 @@ pub enum Error {
     OwnershipRecordNotFound = 8,
     NoTokensToClaim = 9,
     Unauthorized = 10,
+    StreamNotSettled = 11,
+    InvalidExpiration = 12,
+    InvalidBaseUri = 13,
 }
 
+const LEDGER_THRESHOLD: u32 = 518400; // ~30 days at 5s/ledger
+const LEDGER_BUMP: u32 = 535680; // ~31 days
+const LEDGER_SECONDS: u64 = 5;
+
+const NFT_NAME: &str = "Stream Position";
+const NFT_SYMBOL: &str = "STRM";
+const MAX_BASE_URI_LEN: u32 = 256;
+
 #[contracttype]
 #[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub enum StreamStatus {
@@ -61,6 +74,48 @@ pub enum DataKey {
     StreamOwnershipRecord(u64),
     OwnershipToStream(u64),
     Admin,
+    TotalLocked(Address),
+    OwnerBalance(Address),
+    OwnedToken(Address, u32),
+    OwnedTokenIndex(u64),
+    Approval(u64),
+    OperatorApproval(Address, Address),
+    BaseUri,
+}
+
+/// Single-token approval, kept in temporary storage until `live_until_ledger`
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct TokenApproval {
+    pub approved: Address,
+    pub live_until_ledger: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantKind {
+    WithdrawnExceedsTotal,
+    CompletedNotFullyWithdrawn,
+    EscrowShortfall,
+    LiabilitiesOverflow,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvariantViolation {
+    pub stream_id: u64,
+    pub kind: InvariantKind,
+}
+
+/// Result of `check_invariants` over one page of streams
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvariantReport {
+    pub violations: Vec<InvariantViolation>,
+    /// Unclaimed escrow of the walked streams; the sum over all pages should equal `total_locked`
+    pub liabilities: i128,
+    /// Escrow the contract records as locked for the token
+    pub total_locked: i128,
 }
 
 #[contracttype]
@@ -106,6 +161,32 @@ pub struct StreamCancelledEvent {
     pub timestamp: u64,
 }
 
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct StreamClosedEvent {
+    pub stream_id: u64,
+    pub ownership_id: u64,
+    pub status: StreamStatus,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ApprovalEvent {
+    pub ownership_id: u64,
+    pub owner: Address,
+    pub approved: Address,
+    pub live_until_ledger: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ApprovalForAllEvent {
+    pub owner: Address,
+    pub operator: Address,
+    pub live_until_ledger: u32,
+}
+
 #[contract]
 pub struct PaymentStreamContract;
 
@@ -124,6 +205,7 @@ impl PaymentStreamContract {
         env.storage().instance().set(&DataKey::Admin, &admin);
         env.storage().instance().set(&DataKey::StreamCounter, &0u64);
         env.storage().instance().set(&DataKey::OwnershipCounter, &0u64);
+        env.storage().instance().extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
 
         Ok(())
     }
@@ -153,6 +235,7 @@ impl PaymentStreamContract {
 
         let token_client = token::Client::new(&env, &token);
         token_client.transfer(&sender, &env.current_contract_address(), &total_amount);
+        Self::adjust_locked(&env, &token, total_amount);
 
         let stream_id: u64 = env
             .storage()
@@ -183,6 +266,7 @@ impl PaymentStreamContract {
         env.storage()
             .persistent()
             .set(&DataKey::Stream(new_stream_id), &stream);
+        Self::extend_stream_storage(&env, &stream);
 
         env.events().publish(
             (Symbol::new(&env, "stream_created"),),
@@ -202,57 +286,34 @@ impl PaymentStreamContract {
         Ok(new_stream_id)
     }
 
-    pub fn transfer_stream(env: Env, stream_id: u64, new_recipient: Address) -> Result<(), Error> {
-        let mut stream: Stream = env
+    /// Move a stream to `new_recipient`; `caller` is the owner or one of its approved spenders
+    /// Soroban has no implicit caller, so `caller` names the address whose auth is checked
+    pub fn transfer_stream(
+        env: Env,
+        caller: Address,
+        stream_id: u64,
+        new_recipient: Address,
+    ) -> Result<(), Error> {
+        let stream: Stream = env
             .storage()
             .persistent()
             .get(&DataKey::Stream(stream_id))
             .ok_or(Error::StreamNotFound)?;
 
-        if stream.status != StreamStatus::Active {
-            return Err(Error::StreamNotActive);
-        }
-
-        if !stream.transferable {
-            return Err(Error::StreamNotTransferable);
-        }
-
-        let ownership_id = stream.ownership_id;
-        let mut ownership_record: StreamOwnershipRecord = env
+        let ownership_record: StreamOwnershipRecord = env
             .storage()
             .persistent()
-            .get(&DataKey::StreamOwnershipRecord(ownership_id))
+            .get(&DataKey::StreamOwnershipRecord(stream.ownership_id))
             .ok_or(Error::OwnershipRecordNotFound)?;
 
-        ownership_record.owner.require_auth();
-
-        let old_recipient = stream.recipient.clone();
-
-        stream.recipient = new_recipient.clone();
-        env.storage()
-            .persistent()
-            .set(&DataKey::Stream(stream_id), &stream);
-
-        ownership_record.owner = new_recipient.clone();
-        env.storage()
-            .persistent()
-            .set(&DataKey::StreamOwnershipRecord(ownership_id), &ownership_record);
-
-        env.events().publish(
-            (Symbol::new(&env, "stream_transferred"),),
-            StreamTransferredEvent {
-                stream_id,
-                ownership_id,
-                from: old_recipient,
-                to: new_recipient,
-                timestamp: env.ledger().timestamp(),
-            },
-        );
+        Self::require_spender(&env, &ownership_record.owner, &caller, stream.ownership_id)?;
 
-        Ok(())
+        Self::move_position(&env, stream, ownership_record, new_recipient)
     }
 
-    pub fn claim(env: Env, stream_id: u64) -> Result<i128, Error> {
+    /// Pay vested tokens to the stream's owner; `caller` is the owner or one of its approved spenders
+    /// Soroban has no implicit caller, so `caller` names the address whose auth is checked
+    pub fn claim(env: Env, caller: Address, stream_id: u64) -> Result<i128, Error> {
         let mut stream: Stream = env
             .storage()
             .persistent()
@@ -269,7 +330,7 @@ impl PaymentStreamContract {
             .get(&DataKey::StreamOwnershipRecord(stream.ownership_id))
             .ok_or(Error::OwnershipRecordNotFound)?;
 
-        ownership_record.owner.require_auth();
+        Self::require_spender(&env, &ownership_record.owner, &caller, stream.ownership_id)?;
 
         let current_time = env.ledger().timestamp();
         let claimable = Self::calculate_claimable(&stream, current_time);
@@ -279,6 +340,7 @@ impl PaymentStreamContract {
         }
 
         stream.withdrawn_amount += claimable;
+        Self::adjust_locked(&env, &stream.token, -claimable);
 
         if stream.withdrawn_amount >= stream.total_amount {
             stream.status = StreamStatus::Completed;
@@ -287,6 +349,7 @@ impl PaymentStreamContract {
         env.storage()
             .persistent()
             .set(&DataKey::Stream(stream_id), &stream);
+        Self::extend_stream_storage(&env, &stream);
 
         let token_client = token::Client::new(&env, &stream.token);
         token_client.transfer(&env.current_contract_address(), &ownership_record.owner, &claimable);
@@ -327,20 +390,26 @@ impl PaymentStreamContract {
         let current_time = env.ledger().timestamp();
         let vested = Self::calculate_vested(&stream, current_time);
         let refund_amount = stream.total_amount - vested;
+        // Earlier claims already paid part of the vested amount
+        let unclaimed = vested - stream.withdrawn_amount;
 
-        if vested > 0 {
+        if unclaimed > 0 {
             let token_client = token::Client::new(&env, &stream.token);
             token_client.transfer(
                 &env.current_contract_address(),
                 &ownership_record.owner,
-                &vested,
+                &unclaimed,
             );
         }
 
+        Self::adjust_locked(&env, &stream.token, -(stream.total_amount - stream.withdrawn_amount));
+
+        stream.withdrawn_amount = vested;
         stream.status = StreamStatus::Canceled;
         env.storage()
             .persistent()
             .set(&DataKey::Stream(stream_id), &stream);
+        Self::extend_stream_storage(&env, &stream);
 
         if refund_amount > 0 {
             let token_client = token::Client::new(&env, &stream.token);
@@ -365,6 +434,65 @@ impl PaymentStreamContract {
         Ok(())
     }
 
+    pub fn extend_stream_ttl(env: Env, stream_id: u64) -> Result<(), Error> {
+        let stream: Stream = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Stream(stream_id))
+            .ok_or(Error::StreamNotFound)?;
+
+        Self::extend_stream_storage(&env, &stream);
+
+        Ok(())
+    }
+
+    pub fn close_stream(env: Env, stream_id: u64) -> Result<(), Error> {
+        let stream: Stream = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Stream(stream_id))
+            .ok_or(Error::StreamNotFound)?;
+
+        if !matches!(stream.status, StreamStatus::Canceled | StreamStatus::Completed) {
+            return Err(Error::StreamNotSettled);
+        }
+
+        stream.sender.require_auth();
+
+        if let Some(ownership_record) = env
+            .storage()
+            .persistent()
+            .get::<_, StreamOwnershipRecord>(&DataKey::StreamOwnershipRecord(stream.ownership_id))
+        {
+            Self::remove_owned_token(&env, &ownership_record.owner, stream.ownership_id);
+        }
+        env.storage()
+            .temporary()
+            .remove(&DataKey::Approval(stream.ownership_id));
+        env.storage().persistent().remove(&DataKey::Stream(stream_id));
+        env.storage()
+            .persistent()
+            .remove(&DataKey::StreamOwnershipRecord(stream.ownership_id));
+        env.storage()
+            .persistent()
+            .remove(&DataKey::OwnershipToStream(stream.ownership_id));
+        env.storage()
+            .persistent()
+            .remove(&DataKey::OwnedTokenIndex(stream.ownership_id));
+
+        env.events().publish(
+            (Symbol::new(&env, "stream_closed"),),
+            StreamClosedEvent {
+                stream_id,
+                ownership_id: stream.ownership_id,
+                status: stream.status,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+
+        Ok(())
+    }
+
     pub fn get_stream(env: Env, stream_id: u64) -> Result<Stream, Error> {
         env.storage()
             .persistent()
@@ -388,6 +516,215 @@ impl PaymentStreamContract {
         Ok(ownership_record.owner)
     }
 
+    pub fn name(env: Env) -> String {
+        String::from_str(&env, NFT_NAME)
+    }
+
+    pub fn symbol(env: Env) -> String {
+        String::from_str(&env, NFT_SYMBOL)
+    }
+
+    pub fn balance(env: Env, owner: Address) -> u32 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::OwnerBalance(owner))
+            .unwrap_or(0)
+    }
+
+    /// Get up to `limit` ownership ids held by `owner`, starting at index `cursor` of its set
+    /// Removals move the owner's last id into the freed slot, so order is not stable across transfers
+    pub fn tokens_of_owner(env: Env, owner: Address, cursor: u32, limit: u32) -> Vec<u64> {
+        let count = Self::balance(env.clone(), owner.clone());
+        let end = cursor.saturating_add(limit).min(count);
+
+        let mut ownership_ids = Vec::new(&env);
+        for index in cursor..end {
+            let ownership_id: u64 = env
+                .storage()
+                .persistent()
+                .get(&DataKey::OwnedToken(owner.clone(), index))
+                .unwrap();
+            ownership_ids.push_back(ownership_id);
+        }
+
+        ownership_ids
+    }
+
+    pub fn owner_of(env: Env, ownership_id: u64) -> Result<Address, Error> {
+        Self::ownership_record_owner(env, ownership_id)
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, ownership_id: u64) -> Result<(), Error> {
+        from.require_auth();
+
+        let (stream, ownership_record) = Self::load_position(&env, ownership_id)?;
+        if ownership_record.owner != from {
+            return Err(Error::Unauthorized);
+        }
+
+        Self::move_position(&env, stream, ownership_record, to)
+    }
+
+    pub fn transfer_from(
+        env: Env,
+        spender: Address,
+        from: Address,
+        to: Address,
+        ownership_id: u64,
+    ) -> Result<(), Error> {
+        let (stream, ownership_record) = Self::load_position(&env, ownership_id)?;
+        if ownership_record.owner != from {
+            return Err(Error::Unauthorized);
+        }
+        Self::require_spender(&env, &from, &spender, ownership_id)?;
+
+        Self::move_position(&env, stream, ownership_record, to)
+    }
+
+    /// Let `approved` move one position until `live_until_ledger`; the approval is cleared on transfer
+    pub fn approve(
+        env: Env,
+        approver: Address,
+        approved: Address,
+        ownership_id: u64,
+        live_until_ledger: u32,
+    ) -> Result<(), Error> {
+        approver.require_auth();
+
+        let owner = Self::ownership_record_owner(env.clone(), ownership_id)?;
+        if approver != owner
+            && !Self::is_approved_for_all(env.clone(), owner.clone(), approver.clone())
+        {
+            return Err(Error::Unauthorized);
+        }
+        let ttl = Self::approval_ttl(&env, live_until_ledger)?;
+
+        let key = DataKey::Approval(ownership_id);
+        env.storage().temporary().set(
+            &key,
+            &TokenApproval {
+                approved: approved.clone(),
+                live_until_ledger,
+            },
+        );
+        env.storage().temporary().extend_ttl(&key, ttl, ttl);
+
+        env.events().publish(
+            (Symbol::new(&env, "approve"),),
+            ApprovalEvent {
+                ownership_id,
+                owner,
+                approved,
+                live_until_ledger,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Let `operator` move all of the owner's positions until `live_until_ledger`; 0 revokes
+    pub fn approve_for_all(
+        env: Env,
+        owner: Address,
+        operator: Address,
+        live_until_ledger: u32,
+    ) -> Result<(), Error> {
+        owner.require_auth();
+
+        let key = DataKey::OperatorApproval(owner.clone(), operator.clone());
+        if live_until_ledger == 0 {
+            env.storage().temporary().remove(&key);
+        } else {
+            let ttl = Self::approval_ttl(&env, live_until_ledger)?;
+            env.storage().temporary().set(&key, &live_until_ledger);
+            env.storage().temporary().extend_ttl(&key, ttl, ttl);
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "approve_for_all"),),
+            ApprovalForAllEvent {
+                owner,
+                operator,
+                live_until_ledger,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn get_approved(env: Env, ownership_id: u64) -> Option<Address> {
+        let approval: TokenApproval = env
+            .storage()
+            .temporary()
+            .get(&DataKey::Approval(ownership_id))?;
+        if approval.live_until_ledger < env.ledger().sequence() {
+            return None;
+        }
+        Some(approval.approved)
+    }
+
+    pub fn is_approved_for_all(env: Env, owner: Address, operator: Address) -> bool {
+        env.storage()
+            .temporary()
+            .get::<_, u32>(&DataKey::OperatorApproval(owner, operator))
+            .is_some_and(|live_until_ledger| live_until_ledger >= env.ledger().sequence())
+    }
+
+    /// On-chain JSON metadata for a position, with an SVG progress bar
+    /// Positions whose stream was closed fall back to the base URI, if one is set
+    pub fn token_uri(env: Env, ownership_id: u64) -> Result<String, Error> {
+        let (stream, _) = match Self::load_position(&env, ownership_id) {
+            Ok(position) => position,
+            Err(err) => {
+                let minted: u64 = env
+                    .storage()
+                    .instance()
+                    .get(&DataKey::OwnershipCounter)
+                    .unwrap_or(0);
+                return match Self::get_base_uri(env.clone()) {
+                    Some(base_uri) if ownership_id >= 1 && ownership_id <= minted => Ok(
+                        metadata::render_base_uri(&env, &base_uri, ownership_id),
+                    ),
+                    _ => Err(err),
+                };
+            }
+        };
+
+        let now = env.ledger().timestamp();
+        let vested = Self::calculate_vested(&stream, now);
+        let claimable = if stream.status == StreamStatus::Active {
+            Self::calculate_claimable(&stream, now)
+        } else {
+            0
+        };
+
+        Ok(metadata::render_token_uri(&env, ownership_id, &stream, vested, claimable))
+    }
+
+    pub fn set_base_uri(env: Env, base_uri: String) -> Result<(), Error> {
+        let admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .ok_or(Error::Unauthorized)?;
+        admin.require_auth();
+
+        if base_uri.len() > MAX_BASE_URI_LEN {
+            return Err(Error::InvalidBaseUri);
+        }
+
+        env.storage().instance().set(&DataKey::BaseUri, &base_uri);
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+
+        Ok(())
+    }
+
+    pub fn get_base_uri(env: Env) -> Option<String> {
+        env.storage().instance().get(&DataKey::BaseUri)
+    }
+
     pub fn get_claimable(env: Env, stream_id: u64) -> Result<i128, Error> {
         let stream: Stream = env
             .storage()
@@ -402,6 +739,291 @@ impl PaymentStreamContract {
         Ok(Self::calculate_claimable(&stream, env.ledger().timestamp()))
     }
 
+    pub fn get_total_locked(env: Env, token: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::TotalLocked(token))
+            .unwrap_or(0)
+    }
+
+    /// Check escrow invariants for up to `limit` streams of `token`, starting at stream id `cursor`
+    /// The token balance held is checked against `get_total_locked`; tokens held above it are not
+    /// a violation. Callers walking every page can check the summed `liabilities` against `total_locked`
+    pub fn check_invariants(
+        env: Env,
+        token: Address,
+        cursor: u64,
+        limit: u32,
+    ) -> InvariantReport {
+        let mut violations = Vec::new(&env);
+        let mut liabilities: i128 = 0;
+
+        let stream_count: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::StreamCounter)
+            .unwrap_or(0);
+        let start = cursor.max(1);
+        let end = start.saturating_add(limit as u64).min(stream_count + 1);
+
+        for stream_id in start..end {
+            let stream: Stream = match env.storage().persistent().get(&DataKey::Stream(stream_id)) {
+                Some(stream) => stream,
+                None => continue,
+            };
+            if stream.token != token {
+                continue;
+            }
+
+            if stream.withdrawn_amount > stream.total_amount {
+                violations.push_back(InvariantViolation {
+                    stream_id,
+                    kind: InvariantKind::WithdrawnExceedsTotal,
+                });
+            }
+            if stream.status == StreamStatus::Completed
+                && stream.withdrawn_amount != stream.total_amount
+            {
+                violations.push_back(InvariantViolation {
+                    stream_id,
+                    kind: InvariantKind::CompletedNotFullyWithdrawn,
+                });
+            }
+
+            // Canceled streams have already paid out or refunded everything
+            if stream.status != StreamStatus::Canceled {
+                let owed = stream
+                    .total_amount
+                    .checked_sub(stream.withdrawn_amount)
+                    .unwrap_or(i128::MAX)
+                    .max(0);
+                match liabilities.checked_add(owed) {
+                    Some(total) => liabilities = total,
+                    None => violations.push_back(InvariantViolation {
+                        stream_id,
+                        kind: InvariantKind::LiabilitiesOverflow,
+                    }),
+                }
+            }
+        }
+
+        let total_locked = Self::get_total_locked(env.clone(), token.clone());
+        let token_client = token::Client::new(&env, &token);
+        if token_client.balance(&env.current_contract_address()) < total_locked {
+            violations.push_front(InvariantViolation {
+                stream_id: 0,
+                kind: InvariantKind::EscrowShortfall,
+            });
+        }
+
+        InvariantReport {
+            violations,
+            liabilities,
+            total_locked,
+        }
+    }
+
+    fn load_position(env: &Env, ownership_id: u64) -> Result<(Stream, StreamOwnershipRecord), Error> {
+        let ownership_record: StreamOwnershipRecord = env
+            .storage()
+            .persistent()
+            .get(&DataKey::StreamOwnershipRecord(ownership_id))
+            .ok_or(Error::OwnershipRecordNotFound)?;
+        let stream: Stream = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Stream(ownership_record.stream_id))
+            .ok_or(Error::StreamNotFound)?;
+        Ok((stream, ownership_record))
+    }
+
+    /// Hand a stream and its ownership record to `to`; callers have already checked authorization
+    fn move_position(
+        env: &Env,
+        mut stream: Stream,
+        mut ownership_record: StreamOwnershipRecord,
+        to: Address,
+    ) -> Result<(), Error> {
+        if stream.status != StreamStatus::Active {
+            return Err(Error::StreamNotActive);
+        }
+
+        if !stream.transferable {
+            return Err(Error::StreamNotTransferable);
+        }
+
+        let old_recipient = stream.recipient.clone();
+
+        stream.recipient = to.clone();
+        env.storage()
+            .persistent()
+            .set(&DataKey::Stream(stream.id), &stream);
+
+        if ownership_record.owner != to {
+            Self::remove_owned_token(env, &ownership_record.owner, stream.ownership_id);
+            Self::add_owned_token(env, &to, stream.ownership_id);
+        }
+        ownership_record.owner = to.clone();
+        env.storage()
+            .persistent()
+            .set(&DataKey::StreamOwnershipRecord(stream.ownership_id), &ownership_record);
+        env.storage()
+            .temporary()
+            .remove(&DataKey::Approval(stream.ownership_id));
+        Self::extend_stream_storage(env, &stream);
+
+        env.events().publish(
+            (Symbol::new(env, "stream_transferred"),),
+            StreamTransferredEvent {
+                stream_id: stream.id,
+                ownership_id: stream.ownership_id,
+                from: old_recipient,
+                to,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Authenticate `spender` and check it is the owner, the position's approved address or an operator
+    fn require_spender(
+        env: &Env,
+        owner: &Address,
+        spender: &Address,
+        ownership_id: u64,
+    ) -> Result<(), Error> {
+        spender.require_auth();
+
+        if spender == owner
+            || Self::get_approved(env.clone(), ownership_id).as_ref() == Some(spender)
+            || Self::is_approved_for_all(env.clone(), owner.clone(), spender.clone())
+        {
+            Ok(())
+        } else {
+            Err(Error::Unauthorized)
+        }
+    }
+
+    /// Ledgers until `live_until_ledger`, which must not be in the past or beyond the network maximum
+    fn approval_ttl(env: &Env, live_until_ledger: u32) -> Result<u32, Error> {
+        let sequence = env.ledger().sequence();
+        if live_until_ledger < sequence || live_until_ledger > env.ledger().max_live_until_ledger() {
+            return Err(Error::InvalidExpiration);
+        }
+        Ok(live_until_ledger - sequence)
+    }
+
+    /// Append `ownership_id` to the end of `owner`'s set
+    fn add_owned_token(env: &Env, owner: &Address, ownership_id: u64) {
+        let persistent = env.storage().persistent();
+        let index: u32 = persistent
+            .get(&DataKey::OwnerBalance(owner.clone()))
+            .unwrap_or(0);
+
+        Self::set_owner_balance(env, owner, index + 1);
+        Self::set_owned_token(env, owner, index, ownership_id);
+    }
+
+    /// Remove `ownership_id` from `owner`'s set, moving the last id into its slot
+    /// Its reverse index entry is left for the caller to overwrite or remove
+    fn remove_owned_token(env: &Env, owner: &Address, ownership_id: u64) {
+        let persistent = env.storage().persistent();
+        let count: u32 = persistent
+            .get(&DataKey::OwnerBalance(owner.clone()))
+            .unwrap_or(0);
+        let index: u32 = match persistent.get(&DataKey::OwnedTokenIndex(ownership_id)) {
+            Some(index) if index < count => index,
+            _ => return,
+        };
+
+        let last = count - 1;
+        if index != last {
+            let last_id: u64 = persistent
+                .get(&DataKey::OwnedToken(owner.clone(), last))
+                .unwrap();
+            Self::set_owned_token(env, owner, index, last_id);
+        }
+        persistent.remove(&DataKey::OwnedToken(owner.clone(), last));
+        Self::set_owner_balance(env, owner, last);
+    }
+
+    /// Store `ownership_id` at `index`, kept alive as long as the position's stream
+    fn set_owned_token(env: &Env, owner: &Address, index: u32, ownership_id: u64) {
+        let persistent = env.storage().persistent();
+        persistent.set(&DataKey::OwnedToken(owner.clone(), index), &ownership_id);
+        persistent.set(&DataKey::OwnedTokenIndex(ownership_id), &index);
+
+        let ttl = persistent
+            .get::<_, u64>(&DataKey::OwnershipToStream(ownership_id))
+            .and_then(|stream_id| persistent.get::<_, Stream>(&DataKey::Stream(stream_id)))
+            .map_or(LEDGER_BUMP, |stream| Self::stream_ttl(env, &stream));
+        Self::extend_owned_token(env, owner, ownership_id, ttl);
+    }
+
+    /// Extend `owner`'s balance and the index entries of `ownership_id` to `ttl`
+    /// These entries are bumped on every write, so extend whenever they fall short of `ttl`
+    fn extend_owned_token(env: &Env, owner: &Address, ownership_id: u64, ttl: u32) {
+        let persistent = env.storage().persistent();
+        persistent.extend_ttl(&DataKey::OwnerBalance(owner.clone()), ttl, ttl);
+
+        if let Some(index) = persistent.get::<_, u32>(&DataKey::OwnedTokenIndex(ownership_id)) {
+            persistent.extend_ttl(&DataKey::OwnedTokenIndex(ownership_id), ttl, ttl);
+            persistent.extend_ttl(&DataKey::OwnedToken(owner.clone(), index), ttl, ttl);
+        }
+    }
+
+    fn set_owner_balance(env: &Env, owner: &Address, balance: u32) {
+        let key = DataKey::OwnerBalance(owner.clone());
+        env.storage().persistent().set(&key, &balance);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    fn adjust_locked(env: &Env, token: &Address, delta: i128) {
+        let key = DataKey::TotalLocked(token.clone());
+        let locked: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+        env.storage().persistent().set(&key, &(locked + delta));
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    fn stream_ttl(env: &Env, stream: &Stream) -> u32 {
+        let remaining = stream.end_time.saturating_sub(env.ledger().timestamp());
+        let ledgers = (remaining / LEDGER_SECONDS).min(u32::MAX as u64) as u32;
+        ledgers
+            .saturating_add(LEDGER_BUMP)
+            .min(env.storage().max_ttl())
+    }
+
+    fn extend_stream_storage(env: &Env, stream: &Stream) {
+        let ttl = Self::stream_ttl(env, stream);
+        let persistent = env.storage().persistent();
+
+        persistent.extend_ttl(&DataKey::Stream(stream.id), LEDGER_THRESHOLD, ttl);
+        if let Some(ownership_record) = persistent
+            .get::<_, StreamOwnershipRecord>(&DataKey::StreamOwnershipRecord(stream.ownership_id))
+        {
+            Self::extend_owned_token(env, &ownership_record.owner, stream.ownership_id, ttl);
+            persistent.extend_ttl(
+                &DataKey::StreamOwnershipRecord(stream.ownership_id),
+                LEDGER_THRESHOLD,
+                ttl,
+            );
+            persistent.extend_ttl(
+                &DataKey::OwnershipToStream(stream.ownership_id),
+                LEDGER_THRESHOLD,
+                ttl,
+            );
+        }
+
+        env.storage()
+            .instance()
+            .extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
     fn mint_ownership_record(env: Env, recipient: Address, stream_id: u64) -> u64 {
         let ownership_id: u64 = env
             .storage()
@@ -414,6 +1036,7 @@ impl PaymentStreamContract {
             .instance()
             .set(&DataKey::OwnershipCounter, &new_ownership_id);
 
+        Self::add_owned_token(&env, &recipient, new_ownership_id);
         let ownership_record = StreamOwnershipRecord {
             stream_id,
             owner: recipient,
@@ -450,3 +1073,6 @@ impl PaymentStreamContract {
         vested - stream.withdrawn_amount
     }
 }
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,194 @@
+use soroban_sdk::{Env, String};
+
+use crate::{Stream, StreamStatus};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Fixed-capacity text buffer, since the contract has no allocator
+pub(crate) struct TextBuffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> TextBuffer<N> {
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub(crate) fn push_str(&mut self, s: &str) {
+        self.push_bytes(s.as_bytes());
+    }
+
+    pub(crate) fn push_bytes(&mut self, b: &[u8]) {
+        let end = self.len + b.len();
+        self.bytes[self.len..end].copy_from_slice(b);
+        self.len = end;
+    }
+
+    pub(crate) fn push_string(&mut self, s: &String) {
+        let end = self.len + s.len() as usize;
+        s.copy_into_slice(&mut self.bytes[self.len..end]);
+        self.len = end;
+    }
+
+    pub(crate) fn push_u64(&mut self, value: u64) {
+        self.push_u128(value as u128);
+    }
+
+    /// Decimal, with a leading `-` for negative values
+    pub(crate) fn push_i128(&mut self, value: i128) {
+        if value < 0 {
+            self.push_str("-");
+        }
+        self.push_u128(value.unsigned_abs());
+    }
+
+    fn push_u128(&mut self, value: u128) {
+        let mut digits = [0u8; 39];
+        let mut i = digits.len();
+        let mut value = value;
+        loop {
+            i -= 1;
+            digits[i] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        self.push_bytes(&digits[i..]);
+    }
+
+    /// Two-digit zero-padded number, for dates
+    fn push_padded(&mut self, value: u64) {
+        if value < 10 {
+            self.push_str("0");
+        }
+        self.push_u64(value);
+    }
+
+    /// Standard padded base64 of `data`
+    pub(crate) fn push_base64(&mut self, data: &[u8]) {
+        for chunk in data.chunks(3) {
+            let b0 = chunk[0] as u32;
+            let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+            let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+            let triple = (b0 << 16) | (b1 << 8) | b2;
+
+            let mut out = [b'='; 4];
+            for (i, slot) in out.iter_mut().enumerate().take(chunk.len() + 1) {
+                *slot = BASE64_ALPHABET[((triple >> (18 - 6 * i)) & 0x3f) as usize];
+            }
+            self.push_bytes(&out);
+        }
+    }
+
+    /// `YYYY-MM-DD` for a unix timestamp, using the civil-from-days algorithm
+    pub(crate) fn push_date(&mut self, timestamp: u64) {
+        let z = timestamp / SECONDS_PER_DAY + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        self.push_u64(year);
+        self.push_str("-");
+        self.push_padded(month);
+        self.push_str("-");
+        self.push_padded(day);
+    }
+
+    pub(crate) fn to_string(&self, env: &Env) -> String {
+        String::from_bytes(env, self.as_bytes())
+    }
+}
+
+fn status_label(status: StreamStatus) -> &'static str {
+    match status {
+        StreamStatus::Active => "Active",
+        StreamStatus::Paused => "Paused",
+        StreamStatus::Canceled => "Canceled",
+        StreamStatus::Completed => "Completed",
+    }
+}
+
+/// SVG progress bar showing the vested share of the stream
+fn render_progress_svg(stream: &Stream, vested: i128) -> TextBuffer<512> {
+    let vested = vested.clamp(0, stream.total_amount);
+    // Scale the divisor instead of `vested` once the total is large enough, so `vested * 100`
+    // cannot overflow
+    let percent = if stream.total_amount < 100 {
+        vested * 100 / stream.total_amount
+    } else {
+        vested / (stream.total_amount / 100)
+    }
+    .clamp(0, 100) as u64;
+
+    let mut svg = TextBuffer::<512>::new();
+    svg.push_str("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"300\" height=\"60\">");
+    svg.push_str("<rect y=\"28\" width=\"300\" height=\"16\" rx=\"8\" fill=\"#e5e7eb\"/>");
+    svg.push_str("<rect y=\"28\" width=\"");
+    svg.push_u64(percent * 3);
+    svg.push_str("\" height=\"16\" rx=\"8\" fill=\"#4f46e5\"/>");
+    svg.push_str("<text x=\"150\" y=\"18\" text-anchor=\"middle\" font-family=\"sans-serif\" font-size=\"14\">");
+    svg.push_u64(percent);
+    svg.push_str("% vested</text></svg>");
+    svg
+}
+
+/// Base64 `data:` URI with the position's JSON metadata, built from the live stream state
+/// The JSON is encoded because `#`, quotes and spaces are not valid in a raw URI
+pub(crate) fn render_token_uri(
+    env: &Env,
+    ownership_id: u64,
+    stream: &Stream,
+    vested: i128,
+    claimable: i128,
+) -> String {
+    let mut json = TextBuffer::<1536>::new();
+    json.push_str("{\"name\":\"Stream Position #");
+    json.push_u64(ownership_id);
+    json.push_str("\",\"description\":\"Payment stream ");
+    json.push_u64(stream.id);
+    json.push_str("\",\"image\":\"data:image/svg+xml;base64,");
+    json.push_base64(render_progress_svg(stream, vested).as_bytes());
+    json.push_str("\",\"attributes\":[{\"trait_type\":\"token\",\"value\":\"");
+    json.push_string(&stream.token.to_string());
+    json.push_str("\"},{\"trait_type\":\"total\",\"value\":\"");
+    json.push_i128(stream.total_amount);
+    json.push_str("\"},{\"trait_type\":\"vested\",\"value\":\"");
+    json.push_i128(vested);
+    json.push_str("\"},{\"trait_type\":\"claimable\",\"value\":\"");
+    json.push_i128(claimable);
+    json.push_str("\"},{\"trait_type\":\"end_date\",\"value\":\"");
+    json.push_date(stream.end_time);
+    json.push_str("\"},{\"trait_type\":\"status\",\"value\":\"");
+    json.push_str(status_label(stream.status));
+    json.push_str("\"}]}");
+
+    let mut uri = TextBuffer::<2048>::new();
+    uri.push_str("data:application/json;base64,");
+    uri.push_base64(json.as_bytes());
+    uri.to_string(env)
+}
+
+/// `base_uri` followed by the ownership id, for positions with no on-chain state left
+pub(crate) fn render_base_uri(env: &Env, base_uri: &String, ownership_id: u64) -> String {
+    let mut uri = TextBuffer::<512>::new();
+    uri.push_string(base_uri);
+    uri.push_u64(ownership_id);
+    uri.to_string(env)
+}
@@ -0,0 +1,488 @@
+use crate::{
+    DataKey, Error, InvariantKind, InvariantReport, InvariantViolation, PaymentStreamContract,
+    PaymentStreamContractClient, Stream, StreamStatus,
+};
+use soroban_sdk::testutils::storage::Persistent as _;
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::{token, vec, Address, Env, String};
+
+fn setup(env: &Env) -> (PaymentStreamContractClient<'_>, Address, Address) {
+    env.mock_all_auths();
+
+    let admin = Address::generate(env);
+    let sender = Address::generate(env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(env, &contract_id);
+    client.initialize(&admin);
+
+    let token_admin = token::StellarAssetClient::new(env, &token);
+    token_admin.mint(&sender, &10_000);
+
+    (client, sender, token)
+}
+
+#[test]
+fn test_check_invariants_clean() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+
+    let stream_a = client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+    client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+
+    env.ledger().set_timestamp(40);
+    client.claim(&recipient, &stream_a);
+    assert_eq!(client.get_total_locked(&token), 1600);
+
+    env.ledger().set_timestamp(100);
+    client.claim(&recipient, &stream_a);
+
+    assert_eq!(
+        client.check_invariants(&token, &0, &10),
+        InvariantReport {
+            violations: vec![&env],
+            liabilities: 1000,
+            total_locked: 1000,
+        }
+    );
+}
+
+#[test]
+fn test_check_invariants_reports_escrow_shortfall() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+
+    client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+
+    // Stray tokens are surplus, not a violation
+    token::StellarAssetClient::new(&env, &token).mint(&client.address, &5);
+    assert_eq!(client.check_invariants(&token, &1, &10).violations, vec![&env]);
+
+    token::Client::new(&env, &token).transfer(&client.address, &recipient, &10);
+    assert_eq!(
+        client.check_invariants(&token, &1, &10),
+        InvariantReport {
+            violations: vec![
+                &env,
+                InvariantViolation {
+                    stream_id: 0,
+                    kind: InvariantKind::EscrowShortfall,
+                }
+            ],
+            liabilities: 1000,
+            total_locked: 1000,
+        }
+    );
+}
+
+#[test]
+fn test_check_invariants_reports_liabilities_overflow() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+
+    client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+
+    // Corrupted amounts that cannot be summed are reported instead of panicking
+    env.as_contract(&client.address, || {
+        for id in 1..=stream_id {
+            let mut stream: Stream = env.storage().persistent().get(&DataKey::Stream(id)).unwrap();
+            stream.total_amount = i128::MAX;
+            env.storage().persistent().set(&DataKey::Stream(id), &stream);
+        }
+    });
+
+    assert_eq!(
+        client.check_invariants(&token, &1, &10),
+        InvariantReport {
+            violations: vec![
+                &env,
+                InvariantViolation {
+                    stream_id,
+                    kind: InvariantKind::LiabilitiesOverflow,
+                }
+            ],
+            liabilities: i128::MAX,
+            total_locked: 2000,
+        }
+    );
+}
+
+#[test]
+fn test_cancel_after_claim_pays_only_unclaimed() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+    client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+
+    env.ledger().set_timestamp(40);
+    client.claim(&recipient, &stream_id);
+
+    env.ledger().set_timestamp(60);
+    client.cancel_stream(&stream_id);
+
+    let stream = client.get_stream(&stream_id);
+    assert_eq!(stream.status, StreamStatus::Canceled);
+    assert_eq!(stream.withdrawn_amount, 600);
+    assert_eq!(token_client.balance(&recipient), 600);
+    assert_eq!(token_client.balance(&sender), 8400);
+    // The other stream's escrow is untouched
+    assert_eq!(token_client.balance(&client.address), 1000);
+    assert_eq!(client.get_total_locked(&token), 1000);
+    assert_eq!(
+        client.check_invariants(&token, &0, &10),
+        InvariantReport {
+            violations: vec![&env],
+            liabilities: 1000,
+            total_locked: 1000,
+        }
+    );
+}
+
+#[test]
+fn test_stream_ttl_covers_remaining_duration() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+
+    let year: u64 = 365 * 24 * 60 * 60;
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &0, &year, &true);
+    let ownership_id = client.get_stream(&stream_id).ownership_id;
+
+    env.as_contract(&client.address, || {
+        let persistent = env.storage().persistent();
+        assert!(persistent.get_ttl(&DataKey::Stream(stream_id)) as u64 >= year / 5);
+        assert!(
+            persistent.get_ttl(&DataKey::StreamOwnershipRecord(ownership_id)) as u64 >= year / 5
+        );
+        assert!(persistent.get_ttl(&DataKey::OwnerBalance(recipient.clone())) as u64 >= year / 5);
+        assert!(persistent.get_ttl(&DataKey::OwnedTokenIndex(ownership_id)) as u64 >= year / 5);
+        assert!(persistent.get_ttl(&DataKey::OwnedToken(recipient.clone(), 0)) as u64 >= year / 5);
+    });
+
+    client.extend_stream_ttl(&stream_id);
+
+    // A position moved into a freed slot keeps its own stream's TTL
+    let other_id = client.create_stream(&sender, &recipient, &token, &1000, &0, &year, &true);
+    let other_ownership_id = client.get_stream(&other_id).ownership_id;
+    client.transfer_stream(&recipient, &stream_id, &Address::generate(&env));
+    assert_eq!(client.tokens_of_owner(&recipient, &0, &10), vec![&env, other_ownership_id]);
+
+    env.as_contract(&client.address, || {
+        let persistent = env.storage().persistent();
+        assert!(persistent.get_ttl(&DataKey::OwnedToken(recipient.clone(), 0)) as u64 >= year / 5);
+    });
+}
+
+#[test]
+fn test_close_stream() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+    let ownership_id = client.get_stream(&stream_id).ownership_id;
+
+    assert_eq!(
+        client.try_close_stream(&stream_id),
+        Err(Ok(Error::StreamNotSettled))
+    );
+
+    env.ledger().set_timestamp(100);
+    client.claim(&recipient, &stream_id);
+    client.close_stream(&stream_id);
+
+    assert_eq!(
+        client.try_get_stream(&stream_id).err(),
+        Some(Ok(Error::StreamNotFound))
+    );
+    assert_eq!(
+        client.try_get_ownership_record(&ownership_id).err(),
+        Some(Ok(Error::OwnershipRecordNotFound))
+    );
+}
+
+#[test]
+fn test_nft_interface() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    let stream_id = client.create_stream(&sender, &alice, &token, &1000, &0, &100, &true);
+    let ownership_id = client.get_stream(&stream_id).ownership_id;
+
+    assert_eq!(client.name(), String::from_str(&env, "Stream Position"));
+    assert_eq!(client.symbol(), String::from_str(&env, "STRM"));
+    assert_eq!(client.balance(&alice), 1);
+    assert_eq!(client.owner_of(&ownership_id), alice);
+
+    assert_eq!(
+        client.try_transfer(&bob, &alice, &ownership_id),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.transfer(&alice, &bob, &ownership_id);
+
+    assert_eq!(client.owner_of(&ownership_id), bob);
+    assert_eq!(client.get_stream(&stream_id).recipient, bob);
+    assert_eq!(client.balance(&alice), 0);
+    assert_eq!(client.balance(&bob), 1);
+}
+
+#[test]
+fn test_tokens_of_owner() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    let mut stream_ids = vec![&env];
+    for _ in 0..3 {
+        stream_ids.push_back(client.create_stream(&sender, &alice, &token, &1000, &0, &100, &true));
+    }
+    assert_eq!(client.balance(&alice), 3);
+    assert_eq!(client.tokens_of_owner(&alice, &0, &10), vec![&env, 1, 2, 3]);
+    assert_eq!(client.tokens_of_owner(&alice, &1, &1), vec![&env, 2]);
+    assert_eq!(client.tokens_of_owner(&alice, &5, &10), vec![&env]);
+
+    // The last id fills the slot freed by the transfer
+    client.transfer_stream(&alice, &stream_ids.get(0).unwrap(), &bob);
+    assert_eq!(client.tokens_of_owner(&alice, &0, &10), vec![&env, 3, 2]);
+    assert_eq!(client.tokens_of_owner(&bob, &0, &10), vec![&env, 1]);
+    assert_eq!(client.balance(&alice), 2);
+    assert_eq!(client.balance(&bob), 1);
+
+    env.ledger().set_timestamp(100);
+    client.claim(&bob, &stream_ids.get(0).unwrap());
+    client.close_stream(&stream_ids.get(0).unwrap());
+    assert_eq!(client.tokens_of_owner(&bob, &0, &10), vec![&env]);
+    assert_eq!(client.balance(&bob), 0);
+    assert!(!env.as_contract(&client.address, || {
+        env.storage().persistent().has(&DataKey::OwnedTokenIndex(1))
+    }));
+}
+
+#[test]
+fn test_transfer_from_with_approvals() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let spender = Address::generate(&env);
+    let operator = Address::generate(&env);
+
+    let stream_id = client.create_stream(&sender, &alice, &token, &1000, &0, &100, &true);
+    let ownership_id = client.get_stream(&stream_id).ownership_id;
+
+    assert_eq!(
+        client.try_transfer_from(&spender, &alice, &bob, &ownership_id),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.approve(&alice, &spender, &ownership_id, &1000);
+    assert_eq!(client.get_approved(&ownership_id), Some(spender.clone()));
+    client.transfer_from(&spender, &alice, &bob, &ownership_id);
+    assert_eq!(client.owner_of(&ownership_id), bob);
+    // The approval does not survive the transfer
+    assert_eq!(client.get_approved(&ownership_id), None);
+
+    client.approve_for_all(&bob, &operator, &1000);
+    assert!(client.is_approved_for_all(&bob, &operator));
+    client.transfer_from(&operator, &bob, &alice, &ownership_id);
+    assert_eq!(client.owner_of(&ownership_id), alice);
+
+    env.ledger().set_sequence_number(1001);
+    assert!(!client.is_approved_for_all(&bob, &operator));
+    assert_eq!(
+        client.try_approve(&alice, &spender, &ownership_id, &1000),
+        Err(Ok(Error::InvalidExpiration))
+    );
+}
+
+#[test]
+fn test_approved_parties_act_for_owner() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let owner = Address::generate(&env);
+    let custodian = Address::generate(&env);
+    let buyer = Address::generate(&env);
+
+    let stream_id = client.create_stream(&sender, &owner, &token, &1000, &0, &100, &true);
+
+    env.ledger().set_timestamp(50);
+    assert_eq!(
+        client.try_claim(&custodian, &stream_id),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.approve_for_all(&owner, &custodian, &(env.ledger().sequence() + 100));
+    assert!(client.is_approved_for_all(&owner, &custodian));
+
+    // Claimed tokens still go to the owner, not the operator
+    assert_eq!(client.claim(&custodian, &stream_id), 500);
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&owner), 500);
+    assert_eq!(token_client.balance(&custodian), 0);
+
+    client.transfer_stream(&custodian, &stream_id, &buyer);
+    assert_eq!(client.get_stream(&stream_id).recipient, buyer);
+
+    // A live_until_ledger of 0 revokes the operator
+    client.approve_for_all(&owner, &custodian, &0);
+    assert!(!client.is_approved_for_all(&owner, &custodian));
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Decode padded base64 into `out`, returning the decoded length
+fn decode_base64(input: &[u8], out: &mut [u8]) -> usize {
+    fn sextet(c: u8) -> u32 {
+        match c {
+            b'A'..=b'Z' => (c - b'A') as u32,
+            b'a'..=b'z' => (c - b'a' + 26) as u32,
+            b'0'..=b'9' => (c - b'0' + 52) as u32,
+            b'+' => 62,
+            b'/' => 63,
+            _ => panic!("invalid base64 character"),
+        }
+    }
+
+    let mut len = 0;
+    for chunk in input.chunks(4) {
+        let padding = chunk.iter().filter(|&&c| c == b'=').count();
+        let triple = chunk
+            .iter()
+            .fold(0u32, |acc, &c| (acc << 6) | if c == b'=' { 0 } else { sextet(c) });
+        for i in 0..3 - padding {
+            out[len] = (triple >> (16 - 8 * i)) as u8;
+            len += 1;
+        }
+    }
+    len
+}
+
+#[test]
+fn test_token_uri_metadata() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+
+    // Ends 2024-01-01T00:00:00Z
+    let end_time: u64 = 1_704_067_200;
+    env.ledger().set_timestamp(end_time - 1000);
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &1000,
+        &(end_time - 1000),
+        &end_time,
+        &true,
+    );
+    let ownership_id = client.get_stream(&stream_id).ownership_id;
+
+    env.ledger().set_timestamp(end_time - 750);
+    let uri = client.token_uri(&ownership_id);
+    let mut bytes = [0u8; 2048];
+    let uri_bytes = &mut bytes[..uri.len() as usize];
+    uri.copy_into_slice(uri_bytes);
+
+    let prefix = b"data:application/json;base64,";
+    assert!(uri_bytes.starts_with(prefix));
+    // The encoded payload contains no characters that need escaping in a URI
+    assert!(!uri_bytes.iter().any(|c| matches!(c, b'#' | b'"' | b'{' | b' ')));
+
+    let mut json = [0u8; 2048];
+    let json_len = decode_base64(&uri_bytes[prefix.len()..], &mut json);
+    let metadata = &json[..json_len];
+
+    assert!(metadata.starts_with(b"{\"name\":\"Stream Position #1\""));
+    // base64 of "<svg"
+    assert!(contains(metadata, b"\"image\":\"data:image/svg+xml;base64,PHN2Zy"));
+    assert!(contains(metadata, b"{\"trait_type\":\"total\",\"value\":\"1000\"}"));
+    assert!(contains(metadata, b"{\"trait_type\":\"vested\",\"value\":\"250\"}"));
+    assert!(contains(metadata, b"{\"trait_type\":\"claimable\",\"value\":\"250\"}"));
+    assert!(contains(metadata, b"{\"trait_type\":\"end_date\",\"value\":\"2024-01-01\"}"));
+    assert!(contains(metadata, b"{\"trait_type\":\"status\",\"value\":\"Active\"}"));
+
+    let mut token_str = [0u8; 56];
+    token.to_string().copy_into_slice(&mut token_str);
+    assert!(contains(metadata, &token_str));
+}
+
+#[test]
+fn test_token_uri_metadata_large_total() {
+    let env = Env::default();
+    let (client, _, token) = setup(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&sender, &i128::MAX);
+
+    let stream_id =
+        client.create_stream(&sender, &recipient, &token, &i128::MAX, &0, &100, &true);
+    let ownership_id = client.get_stream(&stream_id).ownership_id;
+
+    // Fully vested, so the progress bar computes a percentage of i128::MAX
+    env.ledger().set_timestamp(100);
+    let uri = client.token_uri(&ownership_id);
+    let mut bytes = [0u8; 2048];
+    let uri_bytes = &mut bytes[..uri.len() as usize];
+    uri.copy_into_slice(uri_bytes);
+
+    let prefix = b"data:application/json;base64,";
+    let mut json = [0u8; 2048];
+    let json_len = decode_base64(&uri_bytes[prefix.len()..], &mut json);
+    let metadata = &json[..json_len];
+
+    let image_prefix = b"\"image\":\"data:image/svg+xml;base64,";
+    let image_start = metadata
+        .windows(image_prefix.len())
+        .position(|w| w == image_prefix)
+        .unwrap()
+        + image_prefix.len();
+    let image_len = metadata[image_start..]
+        .iter()
+        .position(|c| *c == b'"')
+        .unwrap();
+    let mut svg = [0u8; 1024];
+    let svg_len = decode_base64(&metadata[image_start..image_start + image_len], &mut svg);
+    assert!(contains(&svg[..svg_len], b">100% vested<"));
+}
+
+#[test]
+fn test_token_uri_base_uri_fallback() {
+    let env = Env::default();
+    let (client, sender, token) = setup(&env);
+    let recipient = Address::generate(&env);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &0, &100, &true);
+    let ownership_id = client.get_stream(&stream_id).ownership_id;
+
+    env.ledger().set_timestamp(100);
+    client.claim(&recipient, &stream_id);
+    client.close_stream(&stream_id);
+
+    assert_eq!(
+        client.try_token_uri(&ownership_id).err(),
+        Some(Ok(Error::OwnershipRecordNotFound))
+    );
+
+    client.set_base_uri(&String::from_str(&env, "https://streams.example/positions/"));
+    assert_eq!(
+        client.token_uri(&ownership_id),
+        String::from_str(&env, "https://streams.example/positions/1")
+    );
+    // Ids that were never minted still have no metadata
+    assert!(client.try_token_uri(&2).is_err());
+}
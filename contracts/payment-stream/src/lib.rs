@@ -1,5 +1,5 @@
 #![no_std]
-use soroban_sdk::{contract, contracterror, contractimpl, contracttype, panic_with_error, token, Address, Env, Symbol};
+use soroban_sdk::{contract, contracterror, contractimpl, contracttype, panic_with_error, token, Address, Env, Symbol, Vec};
 
 /// Stream status enum
 #[contracttype]
@@ -60,6 +60,97 @@ pub struct DelegationRevokedEvent {
     pub recipient: Address,
 }
 
+/// Stream insolvency event data
+#[contracttype]
+#[derive(Clone)]
+pub struct StreamInsolventEvent {
+    pub stream_id: u64,
+    pub debt: i128,
+    pub funded_until: u64,
+}
+
+/// Stream solvency restored event data
+#[contracttype]
+#[derive(Clone)]
+pub struct StreamSolventEvent {
+    pub stream_id: u64,
+}
+
+/// Solvency snapshot of a stream
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolvencyStatus {
+    pub is_solvent: bool,
+    pub debt: i128,
+    pub funded_until: u64,
+}
+
+/// Surplus sweep event data
+#[contracttype]
+#[derive(Clone)]
+pub struct SurplusSweptEvent {
+    pub token: Address,
+    pub to: Address,
+    pub amount: i128,
+}
+
+/// Kind of escrow invariant that was broken
+#[contracttype]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvariantKind {
+    WithdrawnExceedsBalance,
+    BalanceExceedsTotal,
+    CompletedNotFullyWithdrawn,
+    EscrowShortfall,
+    LiabilitiesOverflow,
+}
+
+/// Invariant violation found by `check_invariants`; `stream_id` is 0 for contract-wide checks
+#[contracttype]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvariantViolation {
+    pub stream_id: u64,
+    pub kind: InvariantKind,
+}
+
+/// Result of `check_invariants` over one page of streams
+#[contracttype]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvariantReport {
+    pub violations: Vec<InvariantViolation>,
+    /// Escrow owed to the walked streams; the sum over all pages should equal `total_locked`
+    pub liabilities: i128,
+    /// Escrow the contract records as locked for the token
+    pub total_locked: i128,
+}
+
+/// Stream closed event data
+#[contracttype]
+#[derive(Clone)]
+pub struct StreamClosedEvent {
+    pub stream_id: u64,
+    pub status: StreamStatus,
+}
+
+/// Auto-payout settings chosen by the recipient
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AutoPayoutConfig {
+    pub tip: i128,
+    pub min_interval: u64,
+    pub last_payout: u64,
+}
+
+/// Keeper payout event data
+#[contracttype]
+#[derive(Clone)]
+pub struct AutoPayoutEvent {
+    pub stream_id: u64,
+    pub keeper: Address,
+    pub amount: i128,
+    pub tip: i128,
+}
+
 /// Custom errors for the contract
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
@@ -81,6 +172,11 @@ pub enum Error {
     DepositExceedsTotal = 14,
     ArithmeticOverflow = 15,
     InvalidDelegate = 16,
+    NoSurplus = 17,
+    StreamNotSettled = 18,
+    AutoPayoutNotEnabled = 19,
+    PayoutTooSoon = 20,
+    InvalidInterval = 21,
 }
 
 // consts defined above
@@ -88,6 +184,12 @@ const MAX_FEE: u32 = 500; // 5% in basis points
 
 const LEDGER_THRESHOLD: u32 = 518400; // ~30 days at 5s/ledger
 const LEDGER_BUMP: u32 = 535680; // ~31 days
+const LEDGER_SECONDS: u64 = 5;
+
+const MIN_PAYOUT_INTERVAL: u64 = 3600; // 1 hour between keeper payouts
+
+// Per-stream entries stored under (stream_id, Symbol) keys
+const STREAM_ENTRIES: [&str; 5] = ["delegate", "insolvent", "auto_payout", "tip_pool", "max_tip"];
 
 #[contract]
 pub struct PaymentStreamContract;
@@ -111,6 +213,7 @@ impl PaymentStreamContract {
     }
 
     /// Create a new payment stream
+    #[allow(clippy::too_many_arguments)]
     pub fn create_stream(
         env: Env,
         sender: Address,
@@ -157,12 +260,13 @@ impl PaymentStreamContract {
 
         // Store stream and extend TTL
         env.storage().persistent().set(&stream_id, &stream);
-        env.storage().persistent().extend_ttl(&stream_id, LEDGER_THRESHOLD, LEDGER_BUMP);
+        Self::extend_stream_storage(&env, &stream);
 
         // Transfer tokens from sender to contract (escrow)
         if initial_amount > 0 {
             let token_client = token::Client::new(&env, &token);
             token_client.transfer(&sender, &env.current_contract_address(), &initial_amount);
+            Self::adjust_locked(&env, &token, initial_amount);
         }
 
         stream_id
@@ -195,25 +299,79 @@ impl PaymentStreamContract {
 
         // Update balance
         stream.balance = new_balance;
+        Self::adjust_locked(&env, &stream.token, amount);
 
         env.storage().persistent().set(&stream_id, &stream);
-        env.storage().persistent().extend_ttl(&stream_id, LEDGER_THRESHOLD, LEDGER_BUMP);
+        Self::extend_stream_storage(&env, &stream);
 
         // Emit StreamDeposit event
         env.events().publish(("StreamDeposit", stream_id), StreamDepositEvent { stream_id, amount });
+
+        Self::sync_solvency(&env, &stream);
     }
 
     /// Get stream details (moved up for visibility)
     pub fn get_stream(env: Env, stream_id: u64) -> Stream {
         match env.storage().persistent().get(&stream_id) {
             Some(stream) => {
-                env.storage().persistent().extend_ttl(&stream_id, LEDGER_THRESHOLD, LEDGER_BUMP);
+                Self::extend_stream_storage(&env, &stream);
                 stream
             },
             None => panic_with_error!(&env, Error::StreamNotFound),
         }
     }
 
+    /// Number of ledgers to keep stream entries alive: the remaining duration plus the usual bump
+    fn stream_ttl(env: &Env, stream: &Stream) -> u32 {
+        let remaining = stream.end_time.saturating_sub(env.ledger().timestamp());
+        let ledgers = (remaining / LEDGER_SECONDS).min(u32::MAX as u64) as u32;
+        ledgers.saturating_add(LEDGER_BUMP).min(env.storage().max_ttl())
+    }
+
+    /// Extend the TTL of a stream entry so it outlives the stream's remaining duration
+    fn extend_stream_storage(env: &Env, stream: &Stream) {
+        env.storage().persistent().extend_ttl(&stream.id, LEDGER_THRESHOLD, Self::stream_ttl(env, stream));
+    }
+
+    /// Extend the TTL of a stream and its related entries (callable by anyone)
+    pub fn extend_stream_ttl(env: Env, stream_id: u64) {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+        let ttl = Self::stream_ttl(&env, &stream);
+
+        for entry in STREAM_ENTRIES {
+            let key = (stream_id, Symbol::new(&env, entry));
+            if env.storage().persistent().has(&key) {
+                env.storage().persistent().extend_ttl(&key, LEDGER_THRESHOLD, ttl);
+            }
+        }
+
+        env.storage().instance().extend_ttl(LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    /// Delete storage for a settled (completed or canceled) stream (sender only)
+    pub fn close_stream(env: Env, stream_id: u64) {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+
+        stream.sender.require_auth();
+
+        if !matches!(stream.status, StreamStatus::Canceled | StreamStatus::Completed) {
+            panic_with_error!(&env, Error::StreamNotSettled);
+        }
+
+        Self::refund_tip_pool(&env, &stream);
+
+        env.storage().persistent().remove(&stream_id);
+        for entry in STREAM_ENTRIES {
+            env.storage().persistent().remove(&(stream_id, Symbol::new(&env, entry)));
+        }
+
+        let event = StreamClosedEvent {
+            stream_id,
+            status: stream.status,
+        };
+        env.events().publish(("StreamClosed", stream_id), event);
+    }
+
     /// Assert that the caller is authorized to withdraw (recipient or delegate).
     fn assert_is_recipient_or_delegate(env: &Env, stream_id: u64) {
         let stream: Stream = Self::get_stream(env.clone(), stream_id);
@@ -254,7 +412,7 @@ impl PaymentStreamContract {
     
         // Store delegate
         env.storage().persistent().set(&(stream_id, Symbol::new(&env, "delegate")), &delegate);
-        env.storage().persistent().extend_ttl(&(stream_id, Symbol::new(&env, "delegate")), LEDGER_THRESHOLD, LEDGER_BUMP);
+        env.storage().persistent().extend_ttl(&(stream_id, Symbol::new(&env, "delegate")), LEDGER_THRESHOLD, Self::stream_ttl(&env, &stream));
     
         // Emit event
         let event = DelegationGrantedEvent {
@@ -309,17 +467,8 @@ impl PaymentStreamContract {
     }
 
 
-    /// Calculate withdrawable amount for a stream
-    pub fn withdrawable_amount(env: Env, stream_id: u64) -> i128 {
-        let stream: Stream = Self::get_stream(env.clone(), stream_id);
-
-
-        if stream.status != StreamStatus::Active {
-            return 0;
-        }
-
-        let current_time = env.ledger().timestamp();
-
+    /// Calculate the amount vested at `current_time`, ignoring how much is funded
+    fn calculate_vested(stream: &Stream, current_time: u64) -> i128 {
         if current_time <= stream.start_time {
             return 0;
         }
@@ -331,7 +480,94 @@ impl PaymentStreamContract {
         };
 
         let duration = stream.end_time - stream.start_time;
-        let vested = (stream.total_amount * elapsed as i128) / duration as i128;
+        (stream.total_amount * elapsed as i128) / duration as i128
+    }
+
+    /// Calculate the timestamp up to which the deposited balance covers vesting
+    fn calculate_funded_until(stream: &Stream) -> u64 {
+        if stream.balance >= stream.total_amount {
+            return stream.end_time;
+        }
+
+        let duration = (stream.end_time - stream.start_time) as i128;
+        let funded = (stream.balance.max(0) * duration) / stream.total_amount;
+        stream.start_time + funded as u64
+    }
+
+    /// Calculate the amount vested to the recipient but not backed by deposits
+    fn calculate_debt(env: &Env, stream: &Stream) -> i128 {
+        if matches!(stream.status, StreamStatus::Canceled | StreamStatus::Completed) {
+            return 0;
+        }
+
+        let vested = Self::calculate_vested(stream, env.ledger().timestamp());
+        (vested - stream.balance).max(0)
+    }
+
+    /// Emit an event when a stream crosses into or out of insolvency
+    fn sync_solvency(env: &Env, stream: &Stream) {
+        let insolvent_key = (stream.id, Symbol::new(env, "insolvent"));
+        let was_insolvent = env.storage().persistent().has(&insolvent_key);
+        let debt = Self::calculate_debt(env, stream);
+
+        if debt > 0 && !was_insolvent {
+            env.storage().persistent().set(&insolvent_key, &true);
+            env.storage().persistent().extend_ttl(&insolvent_key, LEDGER_THRESHOLD, Self::stream_ttl(env, stream));
+
+            let event = StreamInsolventEvent {
+                stream_id: stream.id,
+                debt,
+                funded_until: Self::calculate_funded_until(stream),
+            };
+            env.events().publish(("StreamInsolvent", stream.id), event);
+        } else if debt == 0 && was_insolvent {
+            env.storage().persistent().remove(&insolvent_key);
+            env.events().publish(("StreamSolvent", stream.id), StreamSolventEvent { stream_id: stream.id });
+        }
+    }
+
+    /// Get the amount vested to the recipient that the sender has not yet deposited
+    pub fn stream_debt(env: Env, stream_id: u64) -> i128 {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+        Self::calculate_debt(&env, &stream)
+    }
+
+    /// Get the solvency status of a stream, including how long its balance lasts
+    ///
+    /// This is a read-only view. `StreamInsolvent`/`StreamSolvent` events are only
+    /// emitted when `deposit`, `withdraw`, `poke` or `check_solvency` observes the
+    /// stream crossing either way, so a stream that runs dry with no activity stays
+    /// silent until someone calls `check_solvency`.
+    pub fn get_solvency_status(env: Env, stream_id: u64) -> SolvencyStatus {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+        let debt = Self::calculate_debt(&env, &stream);
+
+        SolvencyStatus {
+            is_solvent: debt == 0,
+            debt,
+            funded_until: Self::calculate_funded_until(&stream),
+        }
+    }
+
+    /// Record the current solvency of a stream, emitting `StreamInsolvent` or
+    /// `StreamSolvent` if it crossed either way since it was last observed.
+    /// Anyone may call this, so keepers can surface funding running out.
+    pub fn check_solvency(env: Env, stream_id: u64) -> SolvencyStatus {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+        Self::sync_solvency(&env, &stream);
+        Self::get_solvency_status(env, stream_id)
+    }
+
+    /// Calculate withdrawable amount for a stream
+    pub fn withdrawable_amount(env: Env, stream_id: u64) -> i128 {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+
+
+        if stream.status != StreamStatus::Active {
+            return 0;
+        }
+
+        let vested = Self::calculate_vested(&stream, env.ledger().timestamp());
 
         let available_balance = stream.balance - stream.withdrawn_amount;
         let withdrawable = vested - stream.withdrawn_amount;
@@ -350,31 +586,41 @@ impl PaymentStreamContract {
             panic_with_error!(&env, Error::InsufficientWithdrawable);
         }
 
+        let net_amount = Self::settle_withdrawal(&env, &mut stream, amount);
+
+        // Transfer net amount to recipient
+        let token_client = token::Client::new(&env, &stream.token);
+        token_client.transfer(&env.current_contract_address(), &stream.recipient, &net_amount);
+
+        Self::sync_solvency(&env, &stream);
+    }
+
+    /// Record a withdrawal on the stream and collect the protocol fee, returning the net amount
+    fn settle_withdrawal(env: &Env, stream: &mut Stream, amount: i128) -> i128 {
         // Calculate protocol fee
-        let fee = Self::calculate_protocol_fee(&env, amount);
-        let net_amount = amount - fee;
+        let fee = Self::calculate_protocol_fee(env, amount);
 
         stream.withdrawn_amount += amount;
+        Self::adjust_locked(env, &stream.token, -amount);
 
         // Check if stream is completed
         if stream.withdrawn_amount >= stream.total_amount {
             stream.status = StreamStatus::Completed;
         }
 
-        env.storage().persistent().set(&stream_id, &stream);
-        env.storage().persistent().extend_ttl(&stream_id, LEDGER_THRESHOLD, LEDGER_BUMP);
-
-        // Transfer net amount to recipient
-        let token_client = token::Client::new(&env, &stream.token);
-        token_client.transfer(&env.current_contract_address(), &stream.recipient, &net_amount);
+        env.storage().persistent().set(&stream.id, stream);
+        Self::extend_stream_storage(env, stream);
 
         // Transfer fee to collector if fee > 0
         if fee > 0 {
-            let fee_collector: Address = env.storage().instance().get(&Symbol::new(&env, "fee_collector")).unwrap();
+            let token_client = token::Client::new(env, &stream.token);
+            let fee_collector: Address = env.storage().instance().get(&Symbol::new(env, "fee_collector")).unwrap();
             token_client.transfer(&env.current_contract_address(), &fee_collector, &fee);
             // Emit FeeCollected event
-            env.events().publish(("FeeCollected", stream_id), fee);
+            env.events().publish(("FeeCollected", stream.id), fee);
         }
+
+        amount - fee
     }
 
     /// Withdraw the maximum available amount from a stream
@@ -386,6 +632,172 @@ impl PaymentStreamContract {
         Self::withdraw(env, stream_id, available);
     }
 
+    /// Opt in to keeper payouts, paying `tip` per payout at most once every `min_interval` seconds (recipient only)
+    pub fn set_auto_payout(env: Env, stream_id: u64, tip: i128, min_interval: u64) {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+        stream.recipient.require_auth();
+
+        if tip < 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+        if min_interval < MIN_PAYOUT_INTERVAL {
+            panic_with_error!(&env, Error::InvalidInterval);
+        }
+
+        let config_key = (stream_id, Symbol::new(&env, "auto_payout"));
+        let last_payout = env.storage().persistent()
+            .get::<_, AutoPayoutConfig>(&config_key)
+            .map(|config| config.last_payout)
+            .unwrap_or(0);
+
+        let config = AutoPayoutConfig {
+            tip,
+            min_interval,
+            last_payout,
+        };
+        env.storage().persistent().set(&config_key, &config);
+        env.storage().persistent().extend_ttl(&config_key, LEDGER_THRESHOLD, Self::stream_ttl(&env, &stream));
+    }
+
+    /// Opt out of keeper payouts (recipient only)
+    pub fn disable_auto_payout(env: Env, stream_id: u64) {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+        stream.recipient.require_auth();
+
+        env.storage().persistent().remove(&(stream_id, Symbol::new(&env, "auto_payout")));
+    }
+
+    /// Get the auto-payout settings for a stream
+    pub fn get_auto_payout(env: Env, stream_id: u64) -> Option<AutoPayoutConfig> {
+        // Ensure stream exists
+        Self::get_stream(env.clone(), stream_id);
+        env.storage().persistent().get(&(stream_id, Symbol::new(&env, "auto_payout")))
+    }
+
+    /// Deposit tokens used to pay keeper tips so they are not taken from the recipient, paying at most
+    /// `max_tip` per payout; an `amount` of 0 only updates the cap (sender only)
+    pub fn fund_tips(env: Env, stream_id: u64, amount: i128, max_tip: i128) {
+        let stream: Stream = Self::get_stream(env.clone(), stream_id);
+
+        if matches!(stream.status, StreamStatus::Canceled | StreamStatus::Completed) {
+            panic_with_error!(&env, Error::StreamNotActive);
+        }
+
+        stream.sender.require_auth();
+
+        if amount < 0 || max_tip < 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        let ttl = Self::stream_ttl(&env, &stream);
+
+        if amount > 0 {
+            let token_client = token::Client::new(&env, &stream.token);
+            token_client.transfer(&stream.sender, &env.current_contract_address(), &amount);
+            Self::adjust_locked(&env, &stream.token, amount);
+
+            let pool_key = (stream_id, Symbol::new(&env, "tip_pool"));
+            let pool: i128 = env.storage().persistent().get(&pool_key).unwrap_or(0);
+            env.storage().persistent().set(&pool_key, &(pool + amount));
+            env.storage().persistent().extend_ttl(&pool_key, LEDGER_THRESHOLD, ttl);
+        }
+
+        let max_tip_key = (stream_id, Symbol::new(&env, "max_tip"));
+        env.storage().persistent().set(&max_tip_key, &max_tip);
+        env.storage().persistent().extend_ttl(&max_tip_key, LEDGER_THRESHOLD, ttl);
+    }
+
+    /// Get the most the sender's tip pool pays towards a single keeper payout
+    pub fn get_max_tip(env: Env, stream_id: u64) -> i128 {
+        // Ensure stream exists
+        Self::get_stream(env.clone(), stream_id);
+        env.storage().persistent().get(&(stream_id, Symbol::new(&env, "max_tip"))).unwrap_or(0)
+    }
+
+    /// Get the sender-funded tip balance of a stream
+    pub fn get_tip_pool(env: Env, stream_id: u64) -> i128 {
+        // Ensure stream exists
+        Self::get_stream(env.clone(), stream_id);
+        env.storage().persistent().get(&(stream_id, Symbol::new(&env, "tip_pool"))).unwrap_or(0)
+    }
+
+    /// Return any unused tip balance to the sender
+    fn refund_tip_pool(env: &Env, stream: &Stream) {
+        let pool_key = (stream.id, Symbol::new(env, "tip_pool"));
+        let pool: i128 = env.storage().persistent().get(&pool_key).unwrap_or(0);
+        env.storage().persistent().remove(&pool_key);
+
+        if pool > 0 {
+            let token_client = token::Client::new(env, &stream.token);
+            token_client.transfer(&env.current_contract_address(), &stream.sender, &pool);
+            Self::adjust_locked(env, &stream.token, -pool);
+        }
+    }
+
+    /// Push the withdrawable amount of an auto-payout stream to its recipient, paying the keeper a tip.
+    /// Any address may act as keeper, including the recipient, who then collects the tip itself.
+    /// Because the recipient also chooses the tip, the sender's tip pool covers at most its `max_tip`
+    /// per payout; the rest of the tip comes out of the payout.
+    pub fn poke(env: Env, keeper: Address, stream_id: u64) -> i128 {
+        keeper.require_auth();
+
+        let mut stream: Stream = Self::get_stream(env.clone(), stream_id);
+
+        let config_key = (stream_id, Symbol::new(&env, "auto_payout"));
+        let mut config: AutoPayoutConfig = env.storage().persistent().get(&config_key)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::AutoPayoutNotEnabled));
+
+        let current_time = env.ledger().timestamp();
+        if current_time < config.last_payout.saturating_add(config.min_interval) {
+            panic_with_error!(&env, Error::PayoutTooSoon);
+        }
+
+        let amount = Self::withdrawable_amount(env.clone(), stream_id);
+        if amount <= 0 {
+            panic_with_error!(&env, Error::InsufficientWithdrawable);
+        }
+
+        // The recipient chooses the tip, so the sender caps what its pool pays per payout
+        let pool_key = (stream_id, Symbol::new(&env, "tip_pool"));
+        let pool: i128 = env.storage().persistent().get(&pool_key).unwrap_or(0);
+        let max_tip: i128 = env.storage().persistent().get(&(stream_id, Symbol::new(&env, "max_tip"))).unwrap_or(0);
+        let pool_tip = config.tip.min(max_tip).min(pool);
+        let payout_tip = config.tip - pool_tip;
+
+        if amount - Self::calculate_protocol_fee(&env, amount) <= payout_tip {
+            panic_with_error!(&env, Error::InsufficientWithdrawable);
+        }
+
+        let net_amount = Self::settle_withdrawal(&env, &mut stream, amount);
+        if pool_tip > 0 {
+            env.storage().persistent().set(&pool_key, &(pool - pool_tip));
+            Self::adjust_locked(&env, &stream.token, -pool_tip);
+        }
+        let recipient_amount = net_amount - payout_tip;
+
+        config.last_payout = current_time;
+        env.storage().persistent().set(&config_key, &config);
+        env.storage().persistent().extend_ttl(&config_key, LEDGER_THRESHOLD, Self::stream_ttl(&env, &stream));
+
+        let token_client = token::Client::new(&env, &stream.token);
+        token_client.transfer(&env.current_contract_address(), &stream.recipient, &recipient_amount);
+        if config.tip > 0 {
+            token_client.transfer(&env.current_contract_address(), &keeper, &config.tip);
+        }
+
+        let event = AutoPayoutEvent {
+            stream_id,
+            keeper,
+            amount: recipient_amount,
+            tip: config.tip,
+        };
+        env.events().publish(("AutoPayout", stream_id), event);
+
+        Self::sync_solvency(&env, &stream);
+
+        config.tip
+    }
+
     /// Pause a stream (sender only)
     pub fn pause_stream(env: Env, stream_id: u64) {
       let mut stream: Stream = Self::get_stream(env.clone(), stream_id);
@@ -398,7 +810,7 @@ impl PaymentStreamContract {
         stream.status = StreamStatus::Paused;
 
         env.storage().persistent().set(&stream_id, &stream);
-        env.storage().persistent().extend_ttl(&stream_id, LEDGER_THRESHOLD, LEDGER_BUMP);
+        Self::extend_stream_storage(&env, &stream);
     }
 
     /// Resume a paused stream (sender only)
@@ -413,7 +825,7 @@ impl PaymentStreamContract {
         stream.status = StreamStatus::Active;
 
         env.storage().persistent().set(&stream_id, &stream);
-        env.storage().persistent().extend_ttl(&stream_id, LEDGER_THRESHOLD, LEDGER_BUMP);
+        Self::extend_stream_storage(&env, &stream);
     }
 
     /// Cancel a stream (sender only)
@@ -428,13 +840,16 @@ impl PaymentStreamContract {
         stream.status = StreamStatus::Canceled;
 
         env.storage().persistent().set(&stream_id, &stream);
-        env.storage().persistent().extend_ttl(&stream_id, LEDGER_THRESHOLD, LEDGER_BUMP);
+        Self::extend_stream_storage(&env, &stream);
+        env.storage().persistent().remove(&(stream_id, Symbol::new(&env, "insolvent")));
+        Self::refund_tip_pool(&env, &stream);
 
         // Refund remaining tokens to sender
         let remaining = (stream.balance - stream.withdrawn_amount).max(0);
         if remaining > 0 {
             let token_client = token::Client::new(&env, &stream.token);
             token_client.transfer(&env.current_contract_address(), &stream.sender, &remaining);
+            Self::adjust_locked(&env, &stream.token, -remaining);
         }
     }
 
@@ -469,6 +884,116 @@ impl PaymentStreamContract {
     pub fn get_fee_collector(env: Env) -> Address {
         env.storage().instance().get(&Symbol::new(&env, "fee_collector")).unwrap()
     }
+
+    /// Adjust the amount of `token` held in escrow on behalf of streams
+    fn adjust_locked(env: &Env, token: &Address, delta: i128) {
+        let locked_key = (Symbol::new(env, "locked"), token.clone());
+        let locked: i128 = env.storage().persistent().get(&locked_key).unwrap_or(0);
+        let new_locked = locked.checked_add(delta)
+            .unwrap_or_else(|| panic_with_error!(env, Error::ArithmeticOverflow));
+
+        env.storage().persistent().set(&locked_key, &new_locked);
+        env.storage().persistent().extend_ttl(&locked_key, LEDGER_THRESHOLD, LEDGER_BUMP);
+    }
+
+    /// Get the total amount of `token` the contract owes to streams
+    pub fn get_total_locked(env: Env, token: Address) -> i128 {
+        env.storage().persistent().get(&(Symbol::new(&env, "locked"), token)).unwrap_or(0)
+    }
+
+    /// Check escrow invariants for up to `limit` streams of `token`, starting at stream id `cursor`.
+    /// The token balance held is checked against `get_total_locked`; tokens held above it are surplus
+    /// for `sweep`, not a violation. Callers walking every page can check the summed `liabilities`
+    /// against `total_locked`.
+    pub fn check_invariants(env: Env, token: Address, cursor: u64, limit: u32) -> InvariantReport {
+        let mut violations = Vec::new(&env);
+        let mut liabilities: i128 = 0;
+
+        let stream_count: u64 = env.storage().instance().get(&Symbol::new(&env, "stream_count")).unwrap_or(0);
+        let start = cursor.max(1);
+        let end = start.saturating_add(limit as u64).min(stream_count + 1);
+
+        for stream_id in start..end {
+            let stream: Stream = match env.storage().persistent().get(&stream_id) {
+                Some(stream) => stream,
+                None => continue,
+            };
+            if stream.token != token {
+                continue;
+            }
+
+            if stream.withdrawn_amount > stream.balance {
+                violations.push_back(InvariantViolation {
+                    stream_id,
+                    kind: InvariantKind::WithdrawnExceedsBalance,
+                });
+            }
+            if stream.balance > stream.total_amount {
+                violations.push_back(InvariantViolation {
+                    stream_id,
+                    kind: InvariantKind::BalanceExceedsTotal,
+                });
+            }
+            if stream.status == StreamStatus::Completed && stream.withdrawn_amount != stream.total_amount {
+                violations.push_back(InvariantViolation {
+                    stream_id,
+                    kind: InvariantKind::CompletedNotFullyWithdrawn,
+                });
+            }
+
+            // Canceled streams have already refunded their unwithdrawn balance
+            let unwithdrawn = if stream.status == StreamStatus::Canceled {
+                0
+            } else {
+                stream.balance.checked_sub(stream.withdrawn_amount).unwrap_or(i128::MAX).max(0)
+            };
+            let tip_pool: i128 = env.storage().persistent().get(&(stream_id, Symbol::new(&env, "tip_pool"))).unwrap_or(0);
+
+            match liabilities.checked_add(unwithdrawn).and_then(|total| total.checked_add(tip_pool)) {
+                Some(total) => liabilities = total,
+                None => violations.push_back(InvariantViolation {
+                    stream_id,
+                    kind: InvariantKind::LiabilitiesOverflow,
+                }),
+            }
+        }
+
+        let total_locked = Self::get_total_locked(env.clone(), token.clone());
+        let token_client = token::Client::new(&env, &token);
+        if token_client.balance(&env.current_contract_address()) < total_locked {
+            violations.push_front(InvariantViolation {
+                stream_id: 0,
+                kind: InvariantKind::EscrowShortfall,
+            });
+        }
+
+        InvariantReport { violations, liabilities, total_locked }
+    }
+
+    /// Sweep tokens held above stream liabilities to `to` (admin only)
+    pub fn sweep(env: Env, token: Address, to: Address) -> i128 {
+        let admin: Address = env.storage().instance().get(&Symbol::new(&env, "admin")).unwrap();
+        admin.require_auth();
+
+        let token_client = token::Client::new(&env, &token);
+        let held = token_client.balance(&env.current_contract_address());
+        let surplus = held - Self::get_total_locked(env.clone(), token.clone());
+
+        if surplus <= 0 {
+            panic_with_error!(&env, Error::NoSurplus);
+        }
+
+        token_client.transfer(&env.current_contract_address(), &to, &surplus);
+
+        let event = SurplusSweptEvent {
+            token,
+            to,
+            amount: surplus,
+        };
+        env.events().publish(("SurplusSwept",), event);
+
+        surplus
+    }
 }
 
 mod test;
\ No newline at end of file
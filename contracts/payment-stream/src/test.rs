@@ -1,8 +1,13 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod test {
+    use soroban_sdk::testutils::storage::Persistent as _;
     use soroban_sdk::testutils::{Address as _, Events, Ledger, MockAuth, MockAuthInvoke};
-    use soroban_sdk::{token, Address, Env, IntoVal};
-    use crate::{PaymentStreamContract, PaymentStreamContractClient, StreamStatus};
+    use soroban_sdk::{token, vec, Address, Env, IntoVal, String, TryFromVal};
+    use crate::{
+        InvariantKind, InvariantReport, InvariantViolation, PaymentStreamContract, PaymentStreamContractClient, SolvencyStatus, Stream,
+        StreamStatus,
+    };
 
     #[test]
     fn test_create_stream() {
@@ -993,4 +998,753 @@ fn test_recipient_can_still_withdraw_after_delegate_set() {
     assert_eq!(token_client.balance(&contract_id), 700);
 }
 
+#[test]
+fn test_stream_debt_underfunded() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &1000,
+        &200,
+        &0,
+        &100,
+    );
+
+    // 500 vested, only 200 deposited
+    env.ledger().set_timestamp(50);
+    assert_eq!(client.withdrawable_amount(&stream_id), 200);
+    assert_eq!(client.stream_debt(&stream_id), 300);
+
+    // Withdrawing does not change the debt
+    client.withdraw_max(&stream_id);
+    assert_eq!(client.stream_debt(&stream_id), 300);
+
+    // Topping up clears the debt
+    client.deposit(&stream_id, &300);
+    assert_eq!(client.stream_debt(&stream_id), 0);
+}
+
+#[test]
+fn test_get_solvency_status() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &1000,
+        &250,
+        &0,
+        &100,
+    );
+
+    env.ledger().set_timestamp(20);
+    assert_eq!(
+        client.get_solvency_status(&stream_id),
+        SolvencyStatus { is_solvent: true, debt: 0, funded_until: 25 }
+    );
+
+    env.ledger().set_timestamp(40);
+    assert_eq!(
+        client.get_solvency_status(&stream_id),
+        SolvencyStatus { is_solvent: false, debt: 150, funded_until: 25 }
+    );
+
+    // Fully funded streams are funded until the end
+    client.deposit(&stream_id, &750);
+    assert_eq!(
+        client.get_solvency_status(&stream_id),
+        SolvencyStatus { is_solvent: true, debt: 0, funded_until: 100 }
+    );
+}
+
+#[test]
+fn test_insolvency_events() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &1000,
+        &200,
+        &0,
+        &100,
+    );
+
+    let insolvent_events = |env: &Env| {
+        let topic = String::from_str(env, "StreamInsolvent");
+        env.events()
+            .all()
+            .iter()
+            .filter(|(contract, topics, _)| {
+                *contract == contract_id && String::try_from_val(env, &topics.get(0).unwrap()) == Ok(topic.clone())
+            })
+            .count()
+    };
+
+    env.ledger().set_timestamp(50);
+    client.withdraw(&stream_id, &100);
+    assert_eq!(insolvent_events(&env), 1);
+
+    // Already flagged, no duplicate event
+    client.withdraw(&stream_id, &100);
+    assert_eq!(insolvent_events(&env), 0);
+
+    // A stream that runs dry without any activity is surfaced by check_solvency
+    token_admin.mint(&sender, &1000);
+    let idle_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &1000,
+        &200,
+        &50,
+        &150,
+    );
+
+    env.ledger().set_timestamp(60);
+    assert!(client.check_solvency(&idle_id).is_solvent);
+    assert_eq!(insolvent_events(&env), 0);
+
+    env.ledger().set_timestamp(100);
+    let status = client.check_solvency(&idle_id);
+    assert!(!status.is_solvent);
+    assert_eq!(status.debt, 300);
+    assert_eq!(insolvent_events(&env), 1);
+
+    client.check_solvency(&idle_id);
+    assert_eq!(insolvent_events(&env), 0);
+}
+
+#[test]
+fn test_total_locked_tracking() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &2000);
+
+    let stream_a = client.create_stream(&sender, &recipient, &token, &1000, &600, &0, &100);
+    let stream_b = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+    assert_eq!(client.get_total_locked(&token), 1600);
+
+    client.deposit(&stream_a, &400);
+    assert_eq!(client.get_total_locked(&token), 2000);
+
+    env.ledger().set_timestamp(50);
+    client.withdraw(&stream_a, &300);
+    assert_eq!(client.get_total_locked(&token), 1700);
+
+    client.cancel_stream(&stream_b);
+    assert_eq!(client.get_total_locked(&token), 700);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&contract_id), client.get_total_locked(&token));
+}
+
+#[test]
+fn test_sweep_surplus() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let treasury = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+
+    // Tokens sent straight to the contract by mistake
+    token_admin.mint(&contract_id, &250);
+
+    let swept = client.sweep(&token, &treasury);
+    assert_eq!(swept, 250);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&treasury), 250);
+    assert_eq!(token_client.balance(&contract_id), 1000);
+    assert_eq!(client.get_total_locked(&token), 1000);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #17)")]
+fn test_sweep_without_surplus() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+
+    client.sweep(&token, &admin);
+}
+
+#[test]
+fn test_check_invariants_clean() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &100);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &3000);
+
+    let stream_a = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+    let stream_b = client.create_stream(&sender, &recipient, &token, &1000, &400, &0, &100);
+    client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+
+    env.ledger().set_timestamp(100);
+    client.withdraw_max(&stream_a);
+    client.cancel_stream(&stream_b);
+
+    // Only the third stream still holds escrow
+    assert_eq!(
+        client.check_invariants(&token, &0, &10),
+        InvariantReport { violations: vec![&env], liabilities: 1000, total_locked: 1000 }
+    );
+
+    // Stray tokens are surplus for `sweep`, not a violation
+    token_admin.mint(&contract_id, &7);
+    assert_eq!(client.check_invariants(&token, &0, &10).violations, vec![&env]);
+}
+
+#[test]
+fn test_check_invariants_reports_violations() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &2000);
+
+    client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &500, &0, &100);
+
+    // Corrupt the second stream directly in storage
+    env.as_contract(&contract_id, || {
+        let mut stream: Stream = env.storage().persistent().get(&stream_id).unwrap();
+        stream.withdrawn_amount = 600;
+        stream.status = StreamStatus::Completed;
+        env.storage().persistent().set(&stream_id, &stream);
+    });
+    token::Client::new(&env, &token).transfer(&contract_id, &recipient, &1000);
+
+    let expected = vec![
+        &env,
+        InvariantViolation { stream_id: 0, kind: InvariantKind::EscrowShortfall },
+        InvariantViolation { stream_id, kind: InvariantKind::WithdrawnExceedsBalance },
+        InvariantViolation { stream_id, kind: InvariantKind::CompletedNotFullyWithdrawn },
+    ];
+    assert_eq!(
+        client.check_invariants(&token, &1, &10),
+        InvariantReport { violations: expected, liabilities: 1000, total_locked: 1500 }
+    );
+
+    // The cursor window skips streams outside it, but the shortfall is reported on every page
+    assert_eq!(
+        client.check_invariants(&token, &1, &1),
+        InvariantReport {
+            violations: vec![&env, InvariantViolation { stream_id: 0, kind: InvariantKind::EscrowShortfall }],
+            liabilities: 1000,
+            total_locked: 1500,
+        }
+    );
+    assert_eq!(
+        client.check_invariants(&token, &2, &1),
+        InvariantReport {
+            violations: vec![
+                &env,
+                InvariantViolation { stream_id: 0, kind: InvariantKind::EscrowShortfall },
+                InvariantViolation { stream_id, kind: InvariantKind::WithdrawnExceedsBalance },
+                InvariantViolation { stream_id, kind: InvariantKind::CompletedNotFullyWithdrawn },
+            ],
+            liabilities: 0,
+            total_locked: 1500,
+        }
+    );
+}
+
+#[test]
+fn test_check_invariants_reports_liabilities_overflow() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &2000);
+
+    client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+
+    // Corrupted balances that cannot be summed are reported instead of panicking
+    env.as_contract(&contract_id, || {
+        for id in 1..=stream_id {
+            let mut stream: Stream = env.storage().persistent().get(&id).unwrap();
+            stream.total_amount = i128::MAX;
+            stream.balance = i128::MAX;
+            env.storage().persistent().set(&id, &stream);
+        }
+    });
+
+    assert_eq!(
+        client.check_invariants(&token, &1, &10),
+        InvariantReport {
+            violations: vec![&env, InvariantViolation { stream_id, kind: InvariantKind::LiabilitiesOverflow }],
+            liabilities: i128::MAX,
+            total_locked: 2000,
+        }
+    );
+}
+
+#[test]
+fn test_stream_ttl_covers_remaining_duration() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    // A one year stream outlives the default ~31 day bump
+    let year: u64 = 365 * 24 * 60 * 60;
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &year);
+
+    let ttl = env.as_contract(&contract_id, || env.storage().persistent().get_ttl(&stream_id));
+    assert!(ttl as u64 >= year / 5);
+
+    // Anyone can top the TTL back up later on
+    env.ledger().with_mut(|li| {
+        li.timestamp = year / 2;
+        li.sequence_number += 500_000;
+    });
+    client.extend_stream_ttl(&stream_id);
+
+    let ttl = env.as_contract(&contract_id, || env.storage().persistent().get_ttl(&stream_id));
+    assert!(ttl as u64 >= (year / 2) / 5);
+}
+
+#[test]
+fn test_close_settled_stream() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let delegate = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+    client.set_delegate(&stream_id, &delegate);
+
+    env.ledger().set_timestamp(100);
+    client.withdraw_max(&stream_id);
+    assert_eq!(client.get_stream(&stream_id).status, StreamStatus::Completed);
+
+    client.close_stream(&stream_id);
+
+    assert!(client.try_get_stream(&stream_id).is_err());
+    env.as_contract(&contract_id, || {
+        assert!(!env.storage().persistent().has(&stream_id));
+        assert!(!env.storage().persistent().has(&(stream_id, soroban_sdk::Symbol::new(&env, "delegate"))));
+    });
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #18)")]
+fn test_close_active_stream() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+
+    client.close_stream(&stream_id);
+}
+
+#[test]
+fn test_poke_recipient_funded_tip() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let keeper = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &100_000);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &100_000, &100_000, &0, &10_000);
+    client.set_auto_payout(&stream_id, &10, &3600);
+
+    env.ledger().set_timestamp(5000);
+    let tip = client.poke(&keeper, &stream_id);
+    assert_eq!(tip, 10);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&keeper), 10);
+    assert_eq!(token_client.balance(&recipient), 49_990);
+    assert_eq!(client.get_stream(&stream_id).withdrawn_amount, 50_000);
+    assert_eq!(client.get_auto_payout(&stream_id).unwrap().last_payout, 5000);
+}
+
+#[test]
+fn test_poke_sender_funded_tip() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let keeper = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &100_100);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &100_000, &100_000, &0, &10_000);
+    client.set_auto_payout(&stream_id, &10, &3600);
+    client.fund_tips(&stream_id, &100, &10);
+    assert_eq!(client.get_total_locked(&token), 100_100);
+
+    env.ledger().set_timestamp(5000);
+    client.poke(&keeper, &stream_id);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&keeper), 10);
+    assert_eq!(token_client.balance(&recipient), 50_000);
+    assert_eq!(client.get_tip_pool(&stream_id), 90);
+
+    // Tip pools count towards the escrow liabilities
+    let report = client.check_invariants(&token, &1, &10);
+    assert_eq!(report.liabilities, 50_090);
+    assert_eq!(report.liabilities, report.total_locked);
+
+    // Unused tips go back to the sender on cancel
+    client.cancel_stream(&stream_id);
+    assert_eq!(client.get_tip_pool(&stream_id), 0);
+    assert_eq!(token_client.balance(&sender), 50_090);
+    assert_eq!(client.get_total_locked(&token), 0);
+}
+
+#[test]
+fn test_recipient_keeper_cannot_drain_tip_pool() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &101_000);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &100_000, &100_000, &0, &10_000);
+    client.fund_tips(&stream_id, &1000, &10);
+
+    // The recipient sets a tip as large as the pool and pokes its own stream
+    client.set_auto_payout(&stream_id, &1000, &3600);
+    env.ledger().set_timestamp(5000);
+    client.poke(&recipient, &stream_id);
+
+    // The pool pays only the sender's cap; the rest of the tip comes out of the payout
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(client.get_tip_pool(&stream_id), 990);
+    assert_eq!(token_client.balance(&recipient), 50_010);
+    assert_eq!(client.get_total_locked(&token), 50_990);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #20)")]
+fn test_poke_too_soon() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let keeper = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &100_000);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &100_000, &100_000, &0, &10_000);
+    client.set_auto_payout(&stream_id, &10, &3600);
+
+    env.ledger().set_timestamp(4000);
+    client.poke(&keeper, &stream_id);
+
+    env.ledger().set_timestamp(5000);
+    client.poke(&keeper, &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #19)")]
+fn test_poke_without_auto_payout() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let keeper = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+
+    env.ledger().set_timestamp(50);
+    client.poke(&keeper, &stream_id);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #21)")]
+fn test_auto_payout_interval_too_short() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let fee_collector = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.initialize(&admin, &fee_collector, &0);
+
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&sender, &1000);
+
+    let stream_id = client.create_stream(&sender, &recipient, &token, &1000, &1000, &0, &100);
+
+    client.set_auto_payout(&stream_id, &1, &60);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #6)")]
+fn test_get_tip_pool_stream_not_found() {
+    let env = Env::default();
+
+    let contract_id = env.register(PaymentStreamContract, ());
+    let client = PaymentStreamContractClient::new(&env, &contract_id);
+
+    client.get_tip_pool(&1);
+}
+
 }